@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::Manager;
+
+use crate::markdown::assets;
+
+/// Directories of the open documents, by document. The asset protocol only
+/// serves files inside one of them.
+#[derive(Default)]
+pub struct AssetRoots(Mutex<HashMap<PathBuf, PathBuf>>);
+
+impl AssetRoots {
+    pub fn allow_document(&self, document: &Path) {
+        let Some((key, dir)) = document_dir(document) else {
+            return;
+        };
+        if let Ok(mut roots) = self.0.lock() {
+            roots.insert(key, dir);
+        }
+    }
+
+    /// Stops serving the directory of `document` once it's closed, unless
+    /// another open document is in it too.
+    pub fn forget_document(&self, document: &Path) {
+        let key = document_dir(document).map_or_else(|| document.to_path_buf(), |(key, _)| key);
+        if let Ok(mut roots) = self.0.lock() {
+            roots.remove(&key);
+        }
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.0
+            .lock()
+            .map(|roots| roots.values().any(|root| path.starts_with(root)))
+            .unwrap_or(false)
+    }
+}
+
+/// The document's path with its directory canonicalized, and that
+/// directory. Only the directory is canonicalized so a document that was
+/// already deleted or renamed still maps to the same key.
+fn document_dir(document: &Path) -> Option<(PathBuf, PathBuf)> {
+    let dir = document.parent()?.canonicalize().ok()?;
+    let key = match document.file_name() {
+        Some(name) => dir.join(name),
+        None => dir.clone(),
+    };
+    Some((key, dir))
+}
+
+/// Handler for `arandu-asset://` requests.
+pub fn handle<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    request: &Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    serve(app, request).unwrap_or_else(|status| {
+        Response::builder()
+            .status(status)
+            .body(Vec::new())
+            .unwrap_or_default()
+    })
+}
+
+fn serve<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    request: &Request<Vec<u8>>,
+) -> Result<Response<Vec<u8>>, StatusCode> {
+    let path = assets::asset_path(request.uri().path()).ok_or(StatusCode::BAD_REQUEST)?;
+    // Canonicalizing resolves `..` and symlinks before the root check, so
+    // neither can be used to reach outside the document's directory.
+    let path = path.canonicalize().map_err(|_| StatusCode::NOT_FOUND)?;
+    if !app.state::<AssetRoots>().contains(&path) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let metadata = std::fs::metadata(&path).map_err(|_| StatusCode::NOT_FOUND)?;
+    if !metadata.is_file() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_millis())
+        .unwrap_or_default();
+    let etag = format!("\"{:x}-{:x}\"", metadata.len(), modified);
+
+    // `no-cache` still lets the webview keep the image, but makes it
+    // revalidate against the ETag, so an edited image shows up on the next
+    // reload of the document.
+    let response = Response::builder()
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header(header::ETAG, &etag);
+
+    let not_modified = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|value| value.as_bytes() == etag.as_bytes());
+    if not_modified {
+        return response
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Vec::new())
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let body = std::fs::read(&path).map_err(|_| StatusCode::NOT_FOUND)?;
+    let mime = mime_guess::from_path(&path).first_or_octet_stream();
+    response
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, mime.as_ref())
+        .body(body)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
@@ -7,6 +7,21 @@ use std::process::Command;
 const CLI_SCRIPT: &str = r#"#!/bin/bash
 SOCKET="$HOME/.arandu/arandu.sock"
 
+find_app() {
+    for p in "/Applications/Arandu.app" "$HOME/Applications/Arandu.app"; do
+        [ -d "$p" ] && echo "$p" && return 0
+    done
+    return 1
+}
+
+# Subcomandos (export-pdf, check-links) rodam o binário direto, sem abrir janela
+case "$1" in
+    export-pdf|check-links)
+        APP="$(find_app)" || { echo "Arandu.app not found." >&2; exit 1; }
+        exec "$APP/Contents/MacOS/Arandu" "$@"
+        ;;
+esac
+
 # Se socket existe, usar IPC (caminho rápido)
 if [ -S "$SOCKET" ]; then
     if [ "$#" -eq 0 ]; then
@@ -29,11 +44,7 @@ if [ -S "$SOCKET" ]; then
 fi
 
 # Fallback: método tradicional com open (inicia app se necessário)
-APP=""
-for p in "/Applications/Arandu.app" "$HOME/Applications/Arandu.app"; do
-    [ -d "$p" ] && APP="$p" && break
-done
-[ -z "$APP" ] && echo "Arandu.app not found." >&2 && exit 1
+APP="$(find_app)" || { echo "Arandu.app not found." >&2; exit 1; }
 if [ "$#" -eq 0 ]; then open "$APP"; else
     PATHS=(); for f in "$@"; do
         PATHS+=("$(cd "$(dirname "$f")" 2>/dev/null && echo "$PWD/$(basename "$f")")")
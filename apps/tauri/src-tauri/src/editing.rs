@@ -0,0 +1,141 @@
+//! Changes the app writes back to the documents it shows.
+//!
+//! Every write checks the file still has the hash the view was rendered
+//! from, so an edit made elsewhere in the meantime is never overwritten,
+//! and replaces the file atomically, so a crash can't leave it half
+//! written.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+use crate::markdown;
+
+/// Hex SHA-256 of a file's bytes, as `hash_file` reports it.
+pub fn hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Reads the file at `path`, refusing with a conflict if its hash is no
+/// longer `expected_hash`.
+fn read_unchanged(path: &Path, expected_hash: &str) -> Result<Vec<u8>, String> {
+    let content = std::fs::read(path).map_err(|e| format!("Read error: {}", e))?;
+    if hash(&content) != expected_hash {
+        return Err(format!(
+            "Conflict: {} changed on disk since it was opened",
+            path.display()
+        ));
+    }
+    Ok(content)
+}
+
+/// Writes `content` to a temporary file next to `path` and renames it over
+/// the original, keeping the original's permissions. A symlinked `path` is
+/// resolved first, so the link's target is what gets replaced and the link
+/// itself stays in place.
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<(), String> {
+    let resolved = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let path = resolved.as_path();
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("Not a file: {}", path.display()))?;
+    let temp = path.with_file_name(format!(".{}.arandu-tmp", name.to_string_lossy()));
+
+    let write = || -> std::io::Result<()> {
+        std::fs::write(&temp, content)?;
+        if let Ok(metadata) = std::fs::metadata(path) {
+            std::fs::set_permissions(&temp, metadata.permissions())?;
+        }
+        std::fs::File::open(&temp)?.sync_all()?;
+        std::fs::rename(&temp, path)
+    };
+    write().map_err(|e| {
+        let _ = std::fs::remove_file(&temp);
+        format!("Write error: {}", e)
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskToggle {
+    pub checked: bool,
+    /// Hash of the file after the change, for the next edit's check.
+    pub hash: String,
+}
+
+/// Ticks or unticks the task list item starting on `line` of the markdown
+/// file at `path`.
+#[tauri::command]
+pub fn toggle_task(
+    path: String,
+    line: usize,
+    expected_hash: String,
+    app: tauri::AppHandle,
+) -> Result<TaskToggle, String> {
+    let document = Path::new(&path);
+    if markdown::Format::from_path(document) != markdown::Format::Markdown {
+        return Err("Task lists can only be edited in markdown files".to_string());
+    }
+    let content = String::from_utf8(read_unchanged(document, &expected_hash)?)
+        .map_err(|e| format!("Read error: {}", e))?;
+    let (updated, checked) = markdown::tasks::toggle(&content, line, &crate::render_profile(&app))?;
+    write_atomic(document, updated.as_bytes())?;
+    Ok(TaskToggle {
+        checked,
+        hash: hash(updated.as_bytes()),
+    })
+}
+
+/// Saves the editor's content to `path` and returns the file's new hash.
+/// Refuses with a conflict if the file no longer has `expected_hash`, the
+/// hash it had when it was opened or last saved.
+#[tauri::command]
+pub fn write_file(path: String, content: String, expected_hash: String) -> Result<String, String> {
+    let document = Path::new(&path);
+    read_unchanged(document, &expected_hash)?;
+    write_atomic(document, content.as_bytes())?;
+    Ok(hash(content.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("arandu-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_through_symlinks() {
+        let folder = folder("symlink");
+        let target = folder.join("notes.md");
+        let link = folder.join("link.md");
+        fs::write(&target, "old").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_atomic(&link, b"new").unwrap();
+        let link_metadata = fs::symlink_metadata(&link).unwrap();
+        assert!(link_metadata.file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn refuses_files_changed_on_disk() {
+        let folder = folder("conflict");
+        let path = folder.join("notes.md");
+        fs::write(&path, "opened").unwrap();
+        let opened = hash(b"opened");
+        assert_eq!(read_unchanged(&path, &opened).unwrap(), b"opened");
+
+        fs::write(&path, "changed elsewhere").unwrap();
+        let error = read_unchanged(&path, &opened).unwrap_err();
+        assert!(error.starts_with("Conflict:"), "{}", error);
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
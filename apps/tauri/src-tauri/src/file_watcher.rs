@@ -0,0 +1,606 @@
+//! Live reload for open documents.
+//!
+//! Each document's folder is watched rather than the document itself: an
+//! editor that saves by writing a temporary file and renaming it over the
+//! original replaces the file, and a watch on the replaced file would go
+//! quiet after the first save. Watching the folder keeps following the
+//! path, whatever happens to the file behind it.
+//!
+//! Folders on filesystems the OS can't report changes for (network shares,
+//! WSL's Windows drives, folders shared into a VM or container) are polled
+//! instead, as are folders the watch settings say to poll.
+//!
+//! Events for watched documents reach the frontend as:
+//!
+//! - `file-changed` (path): written, or replaced by a rename or re-creation
+//! - `file-deleted` (path): removed or moved away
+//! - `file-renamed` ([`FileRenamed`]): moved to a new path within its folder
+
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::asset_protocol::AssetRoots;
+use crate::live_render::LiveRenderState;
+
+/// How a folder is watched.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchMode {
+    /// The OS's file events (inotify, FSEvents, ReadDirectoryChangesW).
+    Native,
+    /// Scanning the folder for changes every `poll_interval_ms`.
+    Poll,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct WatchSettings {
+    pub poll_interval_ms: u64,
+    /// Folders watched one way whatever their filesystem, with their
+    /// subfolders. The deepest folder containing a document wins.
+    pub folders: BTreeMap<String, WatchMode>,
+}
+
+impl Default for WatchSettings {
+    fn default() -> Self {
+        WatchSettings {
+            poll_interval_ms: 2000,
+            folders: BTreeMap::new(),
+        }
+    }
+}
+
+const MIN_POLL_INTERVAL_MS: u64 = 250;
+
+impl WatchSettings {
+    fn setting_for(&self, folder: &Path) -> Option<WatchMode> {
+        self.folders
+            .iter()
+            .filter(|(path, _)| folder.starts_with(path))
+            .max_by_key(|(path, _)| path.len())
+            .map(|(_, mode)| *mode)
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.poll_interval_ms.max(MIN_POLL_INTERVAL_MS))
+    }
+}
+
+pub fn settings_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join("watch-settings.json")
+}
+
+pub fn load_settings(app_data_dir: &Path) -> WatchSettings {
+    let path = settings_path(app_data_dir);
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(app_data_dir: &Path, settings: &WatchSettings) -> Result<(), String> {
+    let path = settings_path(app_data_dir);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())
+}
+
+fn watch_settings(app: &tauri::AppHandle) -> WatchSettings {
+    app.path()
+        .app_data_dir()
+        .map(|dir| load_settings(&dir))
+        .unwrap_or_default()
+}
+
+pub struct WatcherState {
+    native: Mutex<Option<notify::RecommendedWatcher>>,
+    /// Created with the poll interval of the settings at the time, and
+    /// replaced when they change.
+    poll: Mutex<Option<notify::PollWatcher>>,
+    /// Watched folders and the watcher each is registered with.
+    folders: Mutex<HashMap<PathBuf, WatchMode>>,
+    /// Canonical paths of the watched documents, shared with the watchers'
+    /// event handler.
+    watched_paths: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl Default for WatcherState {
+    fn default() -> Self {
+        WatcherState {
+            native: Mutex::new(None),
+            poll: Mutex::new(None),
+            folders: Mutex::new(HashMap::new()),
+            watched_paths: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+}
+
+/// How a document is being watched, as `watch_file` reports it.
+#[derive(Debug, Serialize, Clone)]
+pub struct WatchStatus {
+    pub mode: WatchMode,
+    /// The watched folder, as the watch settings name it.
+    pub folder: String,
+    /// The watch setting that applies to the folder, if any; without one
+    /// the mode follows the filesystem.
+    pub setting: Option<WatchMode>,
+    pub poll_interval_ms: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileRenamed {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, PartialEq)]
+enum FileEvent {
+    Changed(PathBuf),
+    Deleted(PathBuf),
+    Renamed(PathBuf, PathBuf),
+}
+
+/// What an event means for the watched documents. inotify reports a
+/// rename as `From`, `To` and then `Both`; the frontend settles the
+/// deletion `From` turns into once the rename arrives. `renamed_to` is the
+/// path of the last `To`, kept until the `Both` for the same rename arrives
+/// so it isn't reported twice.
+fn file_events(
+    event: &Event,
+    watched: &HashSet<PathBuf>,
+    renamed_to: &mut Option<PathBuf>,
+) -> Vec<FileEvent> {
+    let watched_paths = || event.paths.iter().filter(|p| watched.contains(*p)).cloned();
+    match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            let after_to = renamed_to
+                .take_if(|last| event.paths.get(1) == Some(last))
+                .is_some();
+            match &event.paths[..] {
+                // A temporary file renamed over the document: an atomic
+                // save, already reported if the backend sent the `To` on
+                // its own.
+                [_, to] if watched.contains(to) => {
+                    if after_to {
+                        Vec::new()
+                    } else {
+                        vec![FileEvent::Changed(to.clone())]
+                    }
+                }
+                [from, to] if watched.contains(from) => {
+                    vec![FileEvent::Renamed(from.clone(), to.clone())]
+                }
+                _ => Vec::new(),
+            }
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            *renamed_to = event.paths.first().cloned();
+            watched_paths().map(FileEvent::Changed).collect()
+        }
+        EventKind::Create(_) => watched_paths().map(FileEvent::Changed).collect(),
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            watched_paths().map(FileEvent::Deleted).collect()
+        }
+        // Backends that don't say which side of a rename a path is on.
+        EventKind::Modify(ModifyKind::Name(_)) => watched_paths()
+            .map(|path| {
+                if path.exists() {
+                    FileEvent::Changed(path)
+                } else {
+                    FileEvent::Deleted(path)
+                }
+            })
+            .collect(),
+        EventKind::Modify(_) => watched_paths().map(FileEvent::Changed).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn event_handler(
+    app: tauri::AppHandle,
+    watched: Arc<Mutex<HashSet<PathBuf>>>,
+) -> impl FnMut(Result<Event, notify::Error>) + Send + 'static {
+    let mut renamed_to = None;
+    move |res: Result<Event, notify::Error>| {
+        let Ok(event) = res else {
+            return;
+        };
+        let events = match watched.lock() {
+            Ok(watched) => file_events(&event, &watched, &mut renamed_to),
+            Err(_) => return,
+        };
+        for file_event in events {
+            let _ = match file_event {
+                FileEvent::Changed(path) => app.emit("file-changed", path.to_string_lossy()),
+                FileEvent::Deleted(path) => app.emit("file-deleted", path.to_string_lossy()),
+                FileEvent::Renamed(from, to) => app.emit(
+                    "file-renamed",
+                    FileRenamed {
+                        from: from.to_string_lossy().into_owned(),
+                        to: to.to_string_lossy().into_owned(),
+                    },
+                ),
+            };
+        }
+    }
+}
+
+/// Filesystem types whose changes inotify doesn't see: network shares,
+/// WSL's Windows drives (`drvfs`, `9p`), folders shared into a VM, and
+/// FUSE mounts such as sshfs or Docker Desktop's file sharing.
+#[cfg(target_os = "linux")]
+fn polled_filesystem(fstype: &str) -> bool {
+    const POLLED: &[&str] = &[
+        "nfs",
+        "nfs4",
+        "cifs",
+        "smb3",
+        "smbfs",
+        "9p",
+        "drvfs",
+        "virtiofs",
+        "vboxsf",
+        "vmhgfs",
+        "fakeowner",
+        "ceph",
+        "afs",
+    ];
+    POLLED.contains(&fstype) || fstype.starts_with("fuse.")
+}
+
+/// Undoes the octal escapes `/proc/self/mounts` uses for spaces, tabs,
+/// newlines and backslashes in mount points.
+#[cfg(target_os = "linux")]
+fn unescape_mount_point(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(at) = rest.find('\\') {
+        unescaped.push_str(&rest[..at]);
+        match rest
+            .get(at + 1..at + 4)
+            .and_then(|code| u8::from_str_radix(code, 8).ok())
+        {
+            Some(byte) => {
+                unescaped.push(byte as char);
+                rest = &rest[at + 4..];
+            }
+            None => {
+                unescaped.push('\\');
+                rest = &rest[at + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Whether `folder` is on a filesystem the native watcher can't follow,
+/// going by the mount it's under.
+#[cfg(target_os = "linux")]
+fn needs_polling(folder: &Path) -> bool {
+    let Ok(mounts) = std::fs::read_to_string("/proc/self/mounts") else {
+        return false;
+    };
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = PathBuf::from(unescape_mount_point(fields.nth(1)?));
+            Some((mount_point, fields.next()?))
+        })
+        .filter(|(mount_point, _)| folder.starts_with(mount_point))
+        // The deepest mount holds the folder; of equal ones, the last
+        // mounted shadows the others.
+        .max_by_key(|(mount_point, _)| mount_point.as_os_str().len())
+        .is_some_and(|(_, fstype)| polled_filesystem(fstype))
+}
+
+/// FSEvents only reports changes made through this Mac, so anything not
+/// on a local volume is polled.
+#[cfg(target_os = "macos")]
+fn needs_polling(folder: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    let Ok(path) = std::ffi::CString::new(folder.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `statfs` only writes into the zeroed struct it's given.
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+        return false;
+    }
+    stat.f_flags & libc::MNT_LOCAL as u32 == 0
+}
+
+/// Canonical paths on network shares, WSL's `\\wsl$` included, are UNC
+/// paths.
+#[cfg(windows)]
+fn needs_polling(folder: &Path) -> bool {
+    folder.to_string_lossy().starts_with(r"\\?\UNC\")
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn needs_polling(_folder: &Path) -> bool {
+    false
+}
+
+impl WatcherState {
+    /// Starts watching `folder`, natively unless the settings or its
+    /// filesystem call for polling, and returns how it's watched.
+    fn watch_folder(
+        &self,
+        folder: &Path,
+        settings: &WatchSettings,
+        app: &tauri::AppHandle,
+    ) -> Result<WatchMode, String> {
+        let mode = settings.setting_for(folder).unwrap_or_else(|| {
+            if needs_polling(folder) {
+                WatchMode::Poll
+            } else {
+                WatchMode::Native
+            }
+        });
+
+        if mode == WatchMode::Native {
+            let mut guard = self.native.lock().map_err(|e| e.to_string())?;
+            if guard.is_none() {
+                let handler = event_handler(app.clone(), self.watched_paths.clone());
+                *guard = Some(
+                    notify::recommended_watcher(handler)
+                        .map_err(|e| format!("Erro ao criar watcher: {}", e))?,
+                );
+            }
+            match guard
+                .as_mut()
+                .unwrap()
+                .watch(folder, RecursiveMode::NonRecursive)
+            {
+                Ok(()) => return Ok(WatchMode::Native),
+                // Out of inotify watches, say: polling still works.
+                Err(e) => eprintln!("Polling {} instead: {}", folder.display(), e),
+            }
+        }
+
+        let mut guard = self.poll.lock().map_err(|e| e.to_string())?;
+        if guard.is_none() {
+            let handler = event_handler(app.clone(), self.watched_paths.clone());
+            let config = notify::Config::default().with_poll_interval(settings.poll_interval());
+            *guard = Some(
+                notify::PollWatcher::new(handler, config)
+                    .map_err(|e| format!("Erro ao criar watcher: {}", e))?,
+            );
+        }
+        guard
+            .as_mut()
+            .unwrap()
+            .watch(folder, RecursiveMode::NonRecursive)
+            .map_err(|e| format!("Erro ao observar arquivo: {}", e))?;
+        Ok(WatchMode::Poll)
+    }
+
+    fn unwatch_folder(&self, folder: &Path, mode: WatchMode) {
+        match mode {
+            WatchMode::Native => {
+                if let Ok(Some(w)) = self.native.lock().as_deref_mut() {
+                    let _ = w.unwatch(folder);
+                }
+            }
+            WatchMode::Poll => {
+                if let Ok(Some(w)) = self.poll.lock().as_deref_mut() {
+                    let _ = w.unwatch(folder);
+                }
+            }
+        }
+    }
+
+    /// Watches every watched folder again under `settings`, after they
+    /// changed.
+    fn rewatch(&self, settings: &WatchSettings, app: &tauri::AppHandle) -> Result<(), String> {
+        let mut folders = self.folders.lock().map_err(|e| e.to_string())?;
+        for (folder, mode) in folders.iter() {
+            if *mode == WatchMode::Native {
+                self.unwatch_folder(folder, *mode);
+            }
+        }
+        // A new poll watcher picks up the interval.
+        *self.poll.lock().map_err(|e| e.to_string())? = None;
+        for (folder, mode) in folders.iter_mut() {
+            *mode = self.watch_folder(folder, settings, app)?;
+        }
+        Ok(())
+    }
+}
+
+fn folder_of(path: &Path) -> Result<PathBuf, String> {
+    path.parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| format!("Not in a folder: {}", path.display()))
+}
+
+fn watches_folder(watched: &HashSet<PathBuf>, folder: &Path) -> bool {
+    watched.iter().any(|path| path.parent() == Some(folder))
+}
+
+/// Starts following `path`, or reports how it's already followed.
+#[tauri::command]
+pub fn watch_file(
+    path: String,
+    app: tauri::AppHandle,
+    state: tauri::State<WatcherState>,
+    assets: tauri::State<AssetRoots>,
+) -> Result<WatchStatus, String> {
+    let canonical =
+        std::fs::canonicalize(&path).map_err(|e| format!("Erro ao canonicalizar: {}", e))?;
+    assets.allow_document(Path::new(&path));
+    let folder = folder_of(&canonical)?;
+    let settings = watch_settings(&app);
+
+    // The watched set is only locked after the watcher is done: its event
+    // handler locks the set, and `watch` waits on the handler's thread.
+    let mut folders = state.folders.lock().map_err(|e| e.to_string())?;
+    let mode = match folders.get(&folder) {
+        Some(mode) => *mode,
+        None => {
+            let mode = state.watch_folder(&folder, &settings, &app)?;
+            folders.insert(folder.clone(), mode);
+            mode
+        }
+    };
+    state
+        .watched_paths
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(canonical);
+
+    Ok(WatchStatus {
+        mode,
+        folder: folder.to_string_lossy().into_owned(),
+        setting: settings.setting_for(&folder),
+        poll_interval_ms: settings.poll_interval().as_millis() as u64,
+    })
+}
+
+/// Stops following `path`, forgets its last render and stops serving its
+/// images. A path that no longer exists, like the old name of a renamed
+/// document, is taken as given.
+#[tauri::command]
+pub fn unwatch_file(
+    path: String,
+    state: tauri::State<WatcherState>,
+    live: tauri::State<LiveRenderState>,
+    assets: tauri::State<AssetRoots>,
+) -> Result<(), String> {
+    let canonical = std::fs::canonicalize(&path).unwrap_or_else(|_| PathBuf::from(&path));
+    let folder = folder_of(&canonical)?;
+    live.forget(&canonical);
+    assets.forget_document(Path::new(&path));
+
+    let mut folders = state.folders.lock().map_err(|e| e.to_string())?;
+    let folder_still_watched = {
+        let mut watched = state.watched_paths.lock().map_err(|e| e.to_string())?;
+        if !watched.remove(&canonical) {
+            return Ok(());
+        }
+        watches_folder(&watched, &folder)
+    };
+
+    if !folder_still_watched {
+        if let Some(mode) = folders.remove(&folder) {
+            state.unwatch_folder(&folder, mode);
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_watch_settings(app: tauri::AppHandle) -> WatchSettings {
+    watch_settings(&app)
+}
+
+/// Saves `settings` and applies them to the folders being watched.
+#[tauri::command]
+pub fn set_watch_settings(
+    settings: WatchSettings,
+    app: tauri::AppHandle,
+    state: tauri::State<WatcherState>,
+) -> Result<(), String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    save_settings(&app_data_dir, &settings)?;
+    state.rewatch(&settings, &app)
+}
+
+/// Sets how the documents in `folder` and its subfolders are watched;
+/// `None` goes back to following the filesystem.
+#[tauri::command]
+pub fn set_folder_watch_mode(
+    folder: String,
+    mode: Option<WatchMode>,
+    app: tauri::AppHandle,
+    state: tauri::State<WatcherState>,
+) -> Result<(), String> {
+    let mut settings = watch_settings(&app);
+    match mode {
+        Some(mode) => settings.folders.insert(folder, mode),
+        None => settings.folders.remove(&folder),
+    };
+    set_watch_settings(settings, app, state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename(mode: RenameMode, paths: &[&str]) -> Event {
+        let mut event = Event::new(EventKind::Modify(ModifyKind::Name(mode)));
+        for path in paths {
+            event = event.add_path(PathBuf::from(path));
+        }
+        event
+    }
+
+    fn watched() -> HashSet<PathBuf> {
+        HashSet::from([PathBuf::from("/docs/a.md")])
+    }
+
+    #[test]
+    fn atomic_save_is_reported_once() {
+        let watched = watched();
+        let mut renamed_to = None;
+        let from = rename(RenameMode::From, &["/docs/.a.md.tmp"]);
+        let to = rename(RenameMode::To, &["/docs/a.md"]);
+        let both = rename(RenameMode::Both, &["/docs/.a.md.tmp", "/docs/a.md"]);
+        assert!(file_events(&from, &watched, &mut renamed_to).is_empty());
+        assert_eq!(
+            file_events(&to, &watched, &mut renamed_to),
+            vec![FileEvent::Changed(PathBuf::from("/docs/a.md"))]
+        );
+        assert!(file_events(&both, &watched, &mut renamed_to).is_empty());
+        assert_eq!(renamed_to, None);
+    }
+
+    #[test]
+    fn unrelated_events_keep_the_pending_rename() {
+        let watched = watched();
+        let mut renamed_to = None;
+        let to = rename(RenameMode::To, &["/docs/a.md"]);
+        let other = Event::new(EventKind::Create(notify::event::CreateKind::File))
+            .add_path(PathBuf::from("/docs/b.md"));
+        let both = rename(RenameMode::Both, &["/docs/.a.md.tmp", "/docs/a.md"]);
+        file_events(&to, &watched, &mut renamed_to);
+        assert!(file_events(&other, &watched, &mut renamed_to).is_empty());
+        assert!(file_events(&both, &watched, &mut renamed_to).is_empty());
+    }
+
+    #[test]
+    fn rename_only_backends_report_the_save() {
+        let watched = watched();
+        let mut renamed_to = Some(PathBuf::from("/docs/b.md"));
+        let both = rename(RenameMode::Both, &["/docs/.a.md.tmp", "/docs/a.md"]);
+        assert_eq!(
+            file_events(&both, &watched, &mut renamed_to),
+            vec![FileEvent::Changed(PathBuf::from("/docs/a.md"))]
+        );
+        assert_eq!(renamed_to, Some(PathBuf::from("/docs/b.md")));
+    }
+
+    #[test]
+    fn renaming_the_document_away() {
+        let watched = watched();
+        let mut renamed_to = None;
+        let both = rename(RenameMode::Both, &["/docs/a.md", "/docs/c.md"]);
+        assert_eq!(
+            file_events(&both, &watched, &mut renamed_to),
+            vec![FileEvent::Renamed(
+                PathBuf::from("/docs/a.md"),
+                PathBuf::from("/docs/c.md")
+            )]
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Subcommands that do their work and exit without opening a window, for
+//! scripts and CI:
+//!
+//! ```text
+//! arandu export-pdf <file.md> [-o <output.pdf>] [--comments]
+//! arandu check-links <file-or-folder>...
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use crate::markdown::{self, RenderProfile};
+
+/// Tauri keeps app data under a directory named after the bundle
+/// identifier. Must match `identifier` in `tauri.conf.json`.
+const APP_IDENTIFIER: &str = "com.devitools.arandu";
+
+const EXPORT_PDF_USAGE: &str = "usage: arandu export-pdf <file.md> [-o <output.pdf>] [--comments]";
+
+const CHECK_LINKS_USAGE: &str = "usage: arandu check-links <file-or-folder>...";
+
+/// Runs the subcommand named by `args` (without the program name). Returns
+/// the exit code, or `None` when the arguments aren't a subcommand and the
+/// app should start as usual.
+pub fn run(args: &[String]) -> Option<i32> {
+    let command: fn(&[String]) -> Result<(), String> = match args.first().map(String::as_str) {
+        Some("export-pdf") => export_pdf,
+        Some("check-links") => check_links,
+        _ => return None,
+    };
+    attach_console();
+    Some(match command(&args[1..]) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("arandu: {}", e);
+            1
+        }
+    })
+}
+
+/// Release builds on Windows are GUI programs, which start without the
+/// console they were run from, so a subcommand's output and errors would
+/// go nowhere. Output redirected to a file or pipe doesn't need this.
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // Fails when there's no console to attach to, like when run from
+    // Explorer, which leaves things as they were.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+/// The render profile saved by the app, found without a Tauri app handle.
+fn render_profile() -> RenderProfile {
+    dirs::data_dir()
+        .map(|dir| markdown::profile::load_profile(&dir.join(APP_IDENTIFIER)))
+        .unwrap_or_default()
+}
+
+/// Reads the markdown file at `input` and writes it as a PDF to `output`,
+/// with its review comments when `include_comments` is set. Returns a
+/// warning when some of the text couldn't be shown.
+pub fn write_pdf(
+    input: &Path,
+    output: &Path,
+    profile: &RenderProfile,
+    include_comments: bool,
+) -> Result<Option<String>, String> {
+    let content = std::fs::read_to_string(input)
+        .map_err(|e| format!("Failed to read {}: {}", input.display(), e))?;
+    let notes = if include_comments {
+        crate::review_notes(&input.to_string_lossy(), &content, profile)?
+    } else {
+        Vec::new()
+    };
+    let source = markdown::formats::load(&content, Some(input))?;
+    let pdf = markdown::pdf::export(&source.markdown, profile, input, &notes)?;
+    std::fs::write(output, &pdf.bytes)
+        .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+    Ok(pdf.warning())
+}
+
+fn export_pdf(args: &[String]) -> Result<(), String> {
+    let mut input = None;
+    let mut output = None;
+    let mut include_comments = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = Some(PathBuf::from(args.next().ok_or(EXPORT_PDF_USAGE)?));
+            }
+            "--comments" => include_comments = true,
+            "-h" | "--help" => {
+                println!("{}", EXPORT_PDF_USAGE);
+                return Ok(());
+            }
+            _ if input.is_none() && !arg.starts_with('-') => input = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{}'\n{}", arg, EXPORT_PDF_USAGE)),
+        }
+    }
+
+    let input = input.ok_or(EXPORT_PDF_USAGE)?;
+    let output = output.unwrap_or_else(|| input.with_extension("pdf"));
+    if let Some(warning) = write_pdf(&input, &output, &render_profile(), include_comments)? {
+        eprintln!("arandu: warning: {}", warning);
+    }
+    println!("{}", output.display());
+    Ok(())
+}
+
+/// Prints every broken link as `path:line:column: message` and fails when
+/// there is at least one, so CI can gate on it.
+fn check_links(args: &[String]) -> Result<(), String> {
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", CHECK_LINKS_USAGE);
+                return Ok(());
+            }
+            _ if !arg.starts_with('-') => paths.push(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{}'\n{}", arg, CHECK_LINKS_USAGE)),
+        }
+    }
+    if paths.is_empty() {
+        return Err(CHECK_LINKS_USAGE.to_string());
+    }
+
+    let profile = render_profile();
+    let mut count = 0;
+    for path in &paths {
+        if !path.exists() {
+            return Err(format!("{}: No such file or directory", path.display()));
+        }
+        for link in markdown::link_check::check(path, &profile)? {
+            println!(
+                "{}:{}:{}: {}",
+                link.document.display(),
+                link.line,
+                link.column,
+                link.message
+            );
+            count += 1;
+        }
+    }
+
+    match count {
+        0 => Ok(()),
+        1 => Err("1 broken link".to_string()),
+        n => Err(format!("{} broken links", n)),
+    }
+}
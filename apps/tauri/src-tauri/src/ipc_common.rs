@@ -17,7 +17,7 @@
 //!
 //! | Command  | Description                              | Requires `path` |
 //! |----------|------------------------------------------|-----------------|
-//! | `open`   | Open a file in the app and focus window  | Yes             |
+//! | `open`   | Open a file or folder and focus window   | Yes             |
 //! | `ping`   | Health check — always returns success    | No              |
 //! | `show`   | Bring the app window to the foreground   | No              |
 //!
@@ -83,11 +83,11 @@ pub struct IpcResponse {
 ///
 /// # Supported Commands
 ///
-/// - **`open`** — Opens a file in the application. Requires [`IpcCommand::path`]
-///   to be set. The path is canonicalized via [`std::fs::canonicalize`], the main
-///   window is brought to focus, and an `"open-file"` event is emitted to the
-///   frontend. Returns an error if the path is missing, invalid, or the event
-///   fails to emit.
+/// - **`open`** — Opens a file, or a folder as a workspace, in the application.
+///   Requires [`IpcCommand::path`] to be set. The path is canonicalized via
+///   [`std::fs::canonicalize`], the main window is brought to focus, and an
+///   `"open-file"` event is emitted to the frontend. Returns an error if the
+///   path is missing, invalid, or the event fails to emit.
 /// - **`ping`** — A simple health check. Always returns `success: true` with no
 ///   side effects.
 /// - **`show`** — Brings the main application window to the foreground by
@@ -1,30 +1,28 @@
-use comrak::{markdown_to_html, Options};
-use notify::{Event, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use tauri::{Emitter, Manager};
 use tauri_plugin_cli::CliExt;
 use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+use tauri_plugin_opener::OpenerExt;
 
+mod asset_protocol;
 #[cfg(target_os = "macos")]
 mod cli_installer;
+mod editing;
+mod file_watcher;
+mod headless;
 mod history;
 mod ipc_common;
 #[cfg(unix)]
 mod ipc;
+mod live_render;
+mod markdown;
 mod tcp_ipc;
 mod tray;
 mod whisper;
-
-#[derive(Debug, Serialize, Clone)]
-pub struct Heading {
-    level: u8,
-    text: String,
-    index: usize,
-}
+mod workspace;
 
 #[derive(Debug, Serialize, Clone)]
 struct CliStatus {
@@ -46,6 +44,10 @@ struct Comment {
     text: String,
     timestamp: i64,
     resolved: bool,
+    /// First markdown line of the commented blocks. Older comment files
+    /// don't have it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -55,134 +57,311 @@ struct CommentsFile {
     comments: Vec<Comment>,
 }
 
-#[tauri::command]
-fn render_markdown(content: String) -> String {
-    let mut options = Options::default();
-    options.extension.table = true;
-    options.extension.tasklist = true;
-    options.extension.strikethrough = true;
-    options.extension.autolink = true;
-    markdown_to_html(&content, &options)
+fn render_profile(app: &tauri::AppHandle) -> markdown::RenderProfile {
+    app.path()
+        .app_data_dir()
+        .map(|dir| markdown::profile::load_profile(&dir))
+        .unwrap_or_default()
 }
 
 #[tauri::command]
-fn read_file(path: String) -> Result<String, String> {
-    eprintln!("[DEBUG] read_file called with path: {:?}", path);
+fn render_markdown(
+    content: String,
+    theme: Option<markdown::CodeTheme>,
+    path: Option<String>,
+    app: tauri::AppHandle,
+    assets: tauri::State<asset_protocol::AssetRoots>,
+) -> Result<markdown::RenderedMarkdown, String> {
+    let profile = render_profile(&app);
+    let document = path.map(PathBuf::from);
+    if let Some(document) = &document {
+        assets.allow_document(document);
+    }
+    markdown::render(&content, &profile, theme.unwrap_or_default(), document.as_deref())
+}
 
-    // Try to canonicalize the path to handle relative paths correctly
-    let resolved_path = match std::fs::canonicalize(&path) {
-        Ok(p) => {
-            eprintln!("[DEBUG] Canonicalized to: {:?}", p);
-            p
-        }
-        Err(e) => {
-            eprintln!("[DEBUG] Canonicalize failed ({}), trying as-is", e);
-            PathBuf::from(&path)
-        }
-    };
+/// The markdown behind a document's headings and outline, converted the
+/// same way `render_markdown` converts it so line numbers agree with the
+/// rendered blocks.
+fn markdown_source(content: String, path: Option<&str>) -> Result<String, String> {
+    match path {
+        Some(path) => Ok(markdown::formats::load(&content, Some(Path::new(path)))?.markdown),
+        None => Ok(content),
+    }
+}
 
-    std::fs::read_to_string(&resolved_path)
-        .map_err(|e| format!("Failed to read {}: {}", resolved_path.display(), e))
+/// Formats the open dialog offers, markdown first.
+#[tauri::command]
+fn document_formats() -> Vec<markdown::formats::FormatInfo> {
+    markdown::Format::ALL.into_iter().map(markdown::Format::info).collect()
 }
 
+/// Lint diagnostics for the markdown file at `path`, with rule settings
+/// from the nearest `.arandu.toml`. Other formats aren't linted.
 #[tauri::command]
-fn extract_headings(markdown: String) -> Vec<Heading> {
-    let mut headings = Vec::new();
-    let mut index = 0;
-    let mut in_code_block = false;
-
-    for line in markdown.lines() {
-        let trimmed = line.trim();
-        if trimmed.starts_with("```") {
-            in_code_block = !in_code_block;
-            continue;
-        }
-        if in_code_block {
-            continue;
-        }
-        let level = trimmed.chars().take_while(|&c| c == '#').count();
-        if level >= 1 && level <= 4 && trimmed.len() > level {
-            let text = trimmed[level..].trim().to_string();
-            if !text.is_empty() {
-                headings.push(Heading {
-                    level: level as u8,
-                    text,
-                    index,
-                });
-                index += 1;
-            }
-        }
+fn lint_document(
+    path: String,
+    app: tauri::AppHandle,
+) -> Result<Vec<markdown::lint::Diagnostic>, String> {
+    let document = Path::new(&path);
+    if markdown::Format::from_path(document) != markdown::Format::Markdown {
+        return Ok(Vec::new());
     }
-    headings
+    let content =
+        std::fs::read_to_string(document).map_err(|e| format!("Failed to read file: {}", e))?;
+    let config = markdown::lint::config::load(document)?;
+    let profile = render_profile(&app);
+    let arena = comrak::Arena::new();
+    let root = markdown::parse(&arena, &content, &profile);
+    Ok(markdown::lint::check(root, &content, &profile, &config))
 }
 
-struct WatcherState {
-    watcher: Mutex<Option<notify::RecommendedWatcher>>,
-    watched_paths: Mutex<HashSet<PathBuf>>,
+/// Broken links, anchors and images in the document or folder at `path`.
+#[tauri::command]
+fn check_links(
+    path: String,
+    app: tauri::AppHandle,
+) -> Result<Vec<markdown::link_check::BrokenLink>, String> {
+    markdown::link_check::check(Path::new(&path), &render_profile(&app))
 }
 
-struct InitialFile(Mutex<Option<String>>);
+/// A page of the CSV or TSV file at `path`, for the table view's paging
+/// and column sorting.
+#[tauri::command]
+fn read_table_page(
+    path: String,
+    page: usize,
+    sort: Option<markdown::formats::table::Sort>,
+) -> Result<markdown::formats::table::Page, String> {
+    let delimiter = markdown::Format::from_path(Path::new(&path))
+        .delimiter()
+        .ok_or_else(|| format!("Not a table: {}", path))?;
+    let content =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    markdown::formats::table::page(&content, delimiter, page, sort)
+}
 
-pub struct ExplicitQuit(pub Arc<AtomicBool>);
-pub struct IsRecording(pub Arc<AtomicBool>);
+/// The comments saved for the markdown file at `path`, in the order exports
+/// number them.
+fn review_notes(
+    path: &str,
+    content: &str,
+    profile: &markdown::RenderProfile,
+) -> Result<Vec<markdown::ReviewNote>, String> {
+    let comments = load_comments(path.to_string())?.comments;
+    let content = markdown_source(content.to_string(), Some(path))?;
+    let arena = comrak::Arena::new();
+    let headings = markdown::headings::extract(markdown::parse(&arena, &content, profile));
+    let mut notes: Vec<markdown::ReviewNote> = comments
+        .iter()
+        .map(|comment| markdown::ReviewNote {
+            text: comment.text.clone(),
+            timestamp: comment.timestamp,
+            resolved: comment.resolved,
+            line: comment_line(comment, &headings),
+        })
+        .collect();
+    markdown::review::sort(&mut notes);
+    Ok(notes)
+}
 
-fn create_file_watcher(app: tauri::AppHandle) -> Result<notify::RecommendedWatcher, String> {
-    notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
-        if let Ok(event) = res {
-            if event.kind.is_modify() {
-                if let Some(path) = event.paths.first() {
-                    let path_str = path.to_string_lossy().to_string();
-                    let _ = app.emit("file-changed", path_str);
-                }
-            }
-        }
-    })
-    .map_err(|e| format!("Erro ao criar watcher: {}", e))
+/// Writes `path` as a single self-contained HTML file to `output_path`,
+/// with its review comments as endnotes when `include_comments` is set.
+#[tauri::command]
+fn export_html(
+    path: String,
+    output_path: String,
+    theme: Option<markdown::CodeTheme>,
+    include_comments: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let profile = render_profile(&app);
+    let notes = if include_comments.unwrap_or(false) {
+        review_notes(&path, &content, &profile)?
+    } else {
+        Vec::new()
+    };
+    let source = markdown::formats::load(&content, Some(Path::new(&path)))?;
+    let html = markdown::export::standalone_html(
+        &source,
+        &profile,
+        theme.unwrap_or_default(),
+        Path::new(&path),
+        &notes,
+    );
+    std::fs::write(&output_path, html)
+        .map_err(|e| format!("Failed to write {}: {}", output_path, e))
 }
 
+/// Writes `path` as a paginated PDF to `output_path`, and returns a warning
+/// when some of its text couldn't be shown. Layout runs on a blocking
+/// thread, since long documents with images take a moment.
 #[tauri::command]
-fn watch_file(path: String, app: tauri::AppHandle, state: tauri::State<WatcherState>) -> Result<(), String> {
-    let canonical = std::fs::canonicalize(&path)
-        .map_err(|e| format!("Erro ao canonicalizar: {}", e))?;
+async fn export_pdf(
+    path: String,
+    output_path: String,
+    include_comments: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<Option<String>, String> {
+    let profile = render_profile(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        headless::write_pdf(
+            Path::new(&path),
+            Path::new(&output_path),
+            &profile,
+            include_comments.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
-    let mut watched = state.watched_paths.lock().map_err(|e| e.to_string())?;
+/// Writes the review comments on `path` as a Markdown report to
+/// `output_path`.
+#[tauri::command]
+fn export_review_report(path: String, output_path: String, app: tauri::AppHandle) -> Result<(), String> {
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let profile = render_profile(&app);
+    let notes = review_notes(&path, &content, &profile)?;
+    let title = Path::new(&path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let source = markdown_source(content, Some(&path))?;
+    let report = markdown::review::report(&source, &profile, &notes, &title);
+    std::fs::write(&output_path, report)
+        .map_err(|e| format!("Failed to write {}: {}", output_path, e))
+}
 
-    if watched.contains(&canonical) {
-        return Ok(());
+/// Opens a local file that isn't markdown with the system's default
+/// application.
+#[tauri::command]
+fn open_with_system(path: String, app: tauri::AppHandle) -> Result<(), String> {
+    if !Path::new(&path).exists() {
+        return Err(format!("File not found: {}", path));
     }
+    app.opener()
+        .open_path(path, None::<&str>)
+        .map_err(|e| e.to_string())
+}
 
-    let mut guard = state.watcher.lock().map_err(|e| e.to_string())?;
-
-    if guard.is_none() {
-        *guard = Some(create_file_watcher(app.clone())?);
+/// Opens a web or mail link in the default browser or mail client instead
+/// of navigating the app window away.
+#[tauri::command]
+fn open_external_url(url: String, app: tauri::AppHandle) -> Result<(), String> {
+    let allowed = ["http://", "https://", "mailto:"];
+    if !allowed.iter().any(|scheme| url.starts_with(scheme)) {
+        return Err(format!("Unsupported URL: {}", url));
     }
+    app.opener()
+        .open_url(url, None::<&str>)
+        .map_err(|e| e.to_string())
+}
 
-    guard.as_mut()
-        .unwrap()
-        .watch(&canonical, RecursiveMode::NonRecursive)
-        .map_err(|e| format!("Erro ao observar arquivo: {}", e))?;
+#[tauri::command]
+fn get_render_profile(app: tauri::AppHandle) -> Result<markdown::RenderProfile, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(markdown::profile::load_profile(&app_data_dir))
+}
 
-    watched.insert(canonical);
-    Ok(())
+#[tauri::command]
+fn set_render_profile(profile: markdown::RenderProfile, app: tauri::AppHandle) -> Result<(), String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    markdown::profile::save_profile(&app_data_dir, &profile)
 }
 
 #[tauri::command]
-fn unwatch_file(path: String, state: tauri::State<WatcherState>) -> Result<(), String> {
-    let canonical = std::fs::canonicalize(&path)
-        .map_err(|e| format!("Arquivo não encontrado: {}", e))?;
+fn apply_render_preset(
+    preset: markdown::profile::Preset,
+    app: tauri::AppHandle,
+) -> Result<markdown::RenderProfile, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let raw_html = markdown::profile::load_profile(&app_data_dir).raw_html;
+    let profile = markdown::RenderProfile {
+        raw_html,
+        ..preset.profile()
+    };
+    markdown::profile::save_profile(&app_data_dir, &profile)?;
+    Ok(profile)
+}
 
-    let mut watched = state.watched_paths.lock().map_err(|e| e.to_string())?;
+#[tauri::command]
+fn read_file(path: String) -> Result<String, String> {
+    eprintln!("[DEBUG] read_file called with path: {:?}", path);
 
-    if let Ok(mut guard) = state.watcher.lock() {
-        if let Some(w) = guard.as_mut() {
-            let _ = w.unwatch(&canonical);
+    // Try to canonicalize the path to handle relative paths correctly
+    let resolved_path = match std::fs::canonicalize(&path) {
+        Ok(p) => {
+            eprintln!("[DEBUG] Canonicalized to: {:?}", p);
+            p
         }
-    }
+        Err(e) => {
+            eprintln!("[DEBUG] Canonicalize failed ({}), trying as-is", e);
+            PathBuf::from(&path)
+        }
+    };
 
-    watched.remove(&canonical);
-    Ok(())
+    std::fs::read_to_string(&resolved_path)
+        .map_err(|e| format!("Failed to read {}: {}", resolved_path.display(), e))
+}
+
+#[tauri::command]
+fn extract_headings(
+    markdown: String,
+    path: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<Vec<markdown::Heading>, String> {
+    let profile = render_profile(&app);
+    let markdown = markdown_source(markdown, path.as_deref())?;
+    let arena = comrak::Arena::new();
+    let root = markdown::parse(&arena, &markdown, &profile);
+    Ok(markdown::headings::extract(root))
+}
+
+/// Markdown line a comment points at, falling back to the heading ordinal
+/// for comments saved before lines were recorded.
+fn comment_line(comment: &Comment, headings: &[markdown::Heading]) -> Option<usize> {
+    comment.line.or_else(|| {
+        comment.block_ids.iter().find_map(|id| {
+            let index: usize = id.strip_prefix("mkw-heading-")?.parse().ok()?;
+            headings.get(index).map(|h| h.line)
+        })
+    })
+}
+
+#[tauri::command]
+fn extract_outline(
+    markdown: String,
+    markdown_path: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<markdown::Outline, String> {
+    let profile = render_profile(&app);
+    let markdown = markdown_source(markdown, markdown_path.as_deref())?;
+    let arena = comrak::Arena::new();
+    let root = markdown::parse(&arena, &markdown, &profile);
+
+    let comments = match markdown_path {
+        Some(path) => load_comments(path)?.comments,
+        None => Vec::new(),
+    };
+    let headings = markdown::headings::extract(root);
+    let comment_lines: Vec<usize> = comments
+        .iter()
+        .filter(|c| !c.resolved)
+        .filter_map(|c| comment_line(c, &headings))
+        .collect();
+
+    Ok(markdown::outline::build(root, &markdown, &comment_lines))
 }
 
+struct InitialFile(Mutex<Option<String>>);
+
+pub struct ExplicitQuit(pub Arc<AtomicBool>);
+pub struct IsRecording(pub Arc<AtomicBool>);
+
 #[tauri::command]
 fn get_initial_file(state: tauri::State<InitialFile>) -> Option<String> {
     state.0.lock().ok().and_then(|mut guard| guard.take())
@@ -268,11 +447,9 @@ fn save_comments(markdown_path: String, comments_data: CommentsFile) -> Result<(
 
 #[tauri::command]
 fn hash_file(path: String) -> Result<String, String> {
-    use sha2::{Sha256, Digest};
     let content = std::fs::read(&path)
         .map_err(|e| format!("Read error: {}", e))?;
-    let hash = Sha256::digest(&content);
-    Ok(format!("{:x}", hash))
+    Ok(editing::hash(&content))
 }
 
 #[tauri::command]
@@ -330,6 +507,9 @@ fn setup_macos_menu(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>>
     let open_file_item = MenuItemBuilder::with_id("open-file", "Open\u{2026}")
         .accelerator("CmdOrCtrl+O")
         .build(app)?;
+    let open_folder_item = MenuItemBuilder::with_id("open-folder", "Open Folder\u{2026}")
+        .accelerator("CmdOrCtrl+Shift+O")
+        .build(app)?;
 
     let app_submenu = SubmenuBuilder::new(app, "Arandu")
         .about(None)
@@ -341,6 +521,7 @@ fn setup_macos_menu(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>>
 
     let file_submenu = SubmenuBuilder::new(app, "File")
         .item(&open_file_item)
+        .item(&open_folder_item)
         .build()?;
 
     let edit_submenu = SubmenuBuilder::new(app, "Edit")
@@ -376,6 +557,9 @@ fn setup_macos_menu(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>>
             "open-file" => {
                 let _ = app_handle.emit("menu-open-file", ());
             }
+            "open-folder" => {
+                let _ = app_handle.emit("menu-open-folder", ());
+            }
             _ => {}
         }
     });
@@ -383,6 +567,13 @@ fn setup_macos_menu(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>>
     Ok(())
 }
 
+/// Handles command-line subcommands such as `export-pdf` without starting
+/// the app. Returns the exit code if the arguments were one.
+pub fn run_headless() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    headless::run(&args)
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let builder = tauri::Builder::default()
@@ -392,6 +583,7 @@ pub fn run() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_opener::init())
         .plugin(
             tauri_plugin_window_state::Builder::default()
                 .with_denylist(&["recording"])
@@ -420,11 +612,14 @@ pub fn run() {
                 }
             }
         }))
-        .manage(WatcherState {
-            watcher: Mutex::new(None),
-            watched_paths: Mutex::new(HashSet::new()),
-        })
+        .manage(file_watcher::WatcherState::default())
+        .manage(live_render::LiveRenderState::default())
         .manage(InitialFile(Mutex::new(None)))
+        .manage(workspace::WorkspaceState::default())
+        .manage(asset_protocol::AssetRoots::default())
+        .register_uri_scheme_protocol(markdown::assets::SCHEME, |ctx, request| {
+            asset_protocol::handle(ctx.app_handle(), &request)
+        })
         .manage(ExplicitQuit(Arc::new(AtomicBool::new(false))))
         .manage(IsRecording(Arc::new(AtomicBool::new(false))))
         .manage(whisper::commands::RecorderState(Mutex::new(None)))
@@ -529,10 +724,27 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             render_markdown,
+            live_render::render_file,
+            get_render_profile,
+            set_render_profile,
+            apply_render_preset,
+            export_html,
+            export_pdf,
+            export_review_report,
+            document_formats,
+            read_table_page,
+            lint_document,
+            check_links,
+            open_with_system,
+            open_external_url,
             read_file,
             extract_headings,
-            watch_file,
-            unwatch_file,
+            extract_outline,
+            file_watcher::watch_file,
+            file_watcher::unwatch_file,
+            file_watcher::get_watch_settings,
+            file_watcher::set_watch_settings,
+            file_watcher::set_folder_watch_mode,
             get_initial_file,
             check_cli_status,
             install_cli,
@@ -540,6 +752,12 @@ pub fn run() {
             load_comments,
             save_comments,
             hash_file,
+            editing::toggle_task,
+            editing::write_file,
+            workspace::open_workspace,
+            workspace::scan_workspace,
+            workspace::close_workspace,
+            workspace::is_folder,
             history::load_history,
             history::save_history,
             history::add_to_history,
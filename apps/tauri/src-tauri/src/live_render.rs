@@ -0,0 +1,117 @@
+//! Re-rendering open documents when their files change.
+//!
+//! The last render of every watched document is kept, block by block, so a
+//! change on disk reaches the view as the blocks that were added, removed
+//! or modified rather than as a whole new document. The view patches them
+//! in place and keeps its scroll position.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::asset_protocol;
+use crate::editing;
+use crate::markdown::{self, incremental};
+
+struct Snapshot {
+    /// Hash of the file the blocks were rendered from.
+    hash: String,
+    theme: markdown::CodeTheme,
+    profile: markdown::RenderProfile,
+    blocks: Vec<incremental::Block>,
+}
+
+/// The last render of each watched document, by canonical path.
+#[derive(Default)]
+pub struct LiveRenderState {
+    documents: Mutex<HashMap<PathBuf, Snapshot>>,
+}
+
+impl LiveRenderState {
+    /// Drops the render kept for `path`, once it's no longer watched.
+    pub fn forget(&self, path: &Path) {
+        if let Ok(mut documents) = self.documents.lock() {
+            documents.remove(path);
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LiveRender {
+    pub content: String,
+    pub hash: String,
+    pub front_matter: Option<markdown::FrontMatter>,
+    pub blocks: Vec<markdown::SourceBlock>,
+    pub links: Vec<markdown::DocumentLink>,
+    /// The HTML of every top-level block, when the view has to be rendered
+    /// whole.
+    pub html: Option<Vec<String>>,
+    /// Otherwise, what changed since the render the view shows.
+    pub changes: Vec<incremental::BlockChange>,
+}
+
+/// Reads and renders the document at `path`. If the view already shows a
+/// render of it, of the file as it was when its hash was `since`, only
+/// the blocks that changed since are returned.
+#[tauri::command]
+pub fn render_file(
+    path: String,
+    theme: Option<markdown::CodeTheme>,
+    since: Option<String>,
+    app: tauri::AppHandle,
+    state: tauri::State<LiveRenderState>,
+    assets: tauri::State<asset_protocol::AssetRoots>,
+) -> Result<LiveRender, String> {
+    let document = PathBuf::from(&path);
+    let canonical = std::fs::canonicalize(&document).unwrap_or_else(|_| document.clone());
+    let bytes = std::fs::read(&canonical)
+        .map_err(|e| format!("Failed to read {}: {}", canonical.display(), e))?;
+    let hash = editing::hash(&bytes);
+    let content = String::from_utf8(bytes)
+        .map_err(|e| format!("Failed to read {}: {}", canonical.display(), e))?;
+
+    assets.allow_document(&document);
+    let profile = crate::render_profile(&app);
+    let theme = theme.unwrap_or_default();
+    let rendered = incremental::render(&content, &profile, theme, Some(&document))?;
+
+    let mut documents = state.documents.lock().map_err(|e| e.to_string())?;
+    let previous = documents.get(&canonical).filter(|previous| {
+        since.as_deref() == Some(previous.hash.as_str())
+            && previous.theme == theme
+            && previous.profile == profile
+    });
+    let (html, changes) = match previous {
+        Some(previous) => (None, incremental::diff(&previous.blocks, &rendered.html)),
+        None => (
+            Some(
+                rendered
+                    .html
+                    .iter()
+                    .map(|block| block.html.clone())
+                    .collect(),
+            ),
+            Vec::new(),
+        ),
+    };
+    documents.insert(
+        canonical,
+        Snapshot {
+            hash: hash.clone(),
+            theme,
+            profile,
+            blocks: rendered.html,
+        },
+    );
+
+    Ok(LiveRender {
+        content,
+        hash,
+        front_matter: rendered.front_matter,
+        blocks: rendered.blocks,
+        links: rendered.links,
+        html,
+        changes,
+    })
+}
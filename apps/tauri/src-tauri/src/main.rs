@@ -1,5 +1,8 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    if let Some(code) = arandu_lib::run_headless() {
+        std::process::exit(code);
+    }
     arandu_lib::run()
 }
@@ -0,0 +1,66 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+use super::links;
+
+/// URI scheme the app registers for serving files next to a document.
+pub const SCHEME: &str = "arandu-asset";
+
+/// Windows and Android webviews only load custom protocols through an
+/// `http://<scheme>.localhost` origin; everywhere else the scheme is used
+/// as is.
+#[cfg(any(windows, target_os = "android"))]
+const ORIGIN: &str = "http://arandu-asset.localhost";
+#[cfg(not(any(windows, target_os = "android")))]
+const ORIGIN: &str = "arandu-asset://localhost";
+
+/// URL the webview can load `path` from.
+pub fn asset_url(path: &Path) -> Option<String> {
+    let file = Url::from_file_path(path).ok()?;
+    Some(format!("{}{}", ORIGIN, file.path()))
+}
+
+/// Turns the (still percent-encoded) path of an asset request back into a
+/// file path. Inverse of [`asset_url`].
+pub fn asset_path(uri_path: &str) -> Option<PathBuf> {
+    Url::parse(&format!("file://{}", uri_path))
+        .ok()?
+        .to_file_path()
+        .ok()
+}
+
+/// Image rewriter for comrak: relative `src` attributes are resolved
+/// against the document and pointed at the asset scheme. Remote images are
+/// left untouched.
+pub fn rewriter(document: PathBuf) -> impl Fn(&str) -> String + Send + Sync {
+    move |url: &str| {
+        links::resolve(url, Some(&document))
+            .and_then(|link| link.path)
+            .and_then(|path| asset_url(&path))
+            .unwrap_or_else(|| url.to_string())
+    }
+}
+
+/// Local image as a `data:` URI. Anything that isn't an image by extension
+/// is refused, so a link can't pull arbitrary files into an export.
+pub fn data_uri(path: &Path) -> Option<String> {
+    let mime = mime_guess::from_path(path).first()?;
+    if mime.type_() != mime_guess::mime::IMAGE {
+        return None;
+    }
+    let bytes = std::fs::read(path).ok()?;
+    Some(format!("data:{};base64,{}", mime, STANDARD.encode(bytes)))
+}
+
+/// Image rewriter for exports: local images are embedded into the HTML.
+/// Images that can't be read keep their original URL.
+pub fn embedding_rewriter(document: PathBuf) -> impl Fn(&str) -> String + Send + Sync {
+    move |url: &str| {
+        links::resolve(url, Some(&document))
+            .and_then(|link| link.path)
+            .and_then(|path| data_uri(&path))
+            .unwrap_or_else(|| url.to_string())
+    }
+}
@@ -0,0 +1,156 @@
+use comrak::html;
+use comrak::nodes::{AstNode, NodeValue};
+use layout::backends::svg::SVGWriter;
+use layout::gv::{DotParser, GraphBuilder};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Mutex, OnceLock};
+
+/// The cache is emptied once it holds this many diagrams.
+const CACHE_LIMIT: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Dot,
+    Svgbob,
+    Pikchr,
+}
+
+impl Kind {
+    fn from_info(info: &str) -> Option<Self> {
+        match info.split_whitespace().next()? {
+            "dot" | "graphviz" => Some(Kind::Dot),
+            "svgbob" | "bob" => Some(Kind::Svgbob),
+            "pikchr" => Some(Kind::Pikchr),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Dot => "dot",
+            Kind::Svgbob => "svgbob",
+            Kind::Pikchr => "pikchr",
+        }
+    }
+
+    fn render(self, source: &str) -> Result<String, String> {
+        match self {
+            Kind::Dot => render_dot(source),
+            Kind::Svgbob => Ok(svgbob::to_svg(source)),
+            // pikchr only has its C reference implementation, which would
+            // put a C toolchain in the build. Fences still get a box saying
+            // why they aren't drawn, rather than passing for plain code.
+            Kind::Pikchr => Err("pikchr diagrams aren't supported yet".to_string()),
+        }
+    }
+}
+
+fn render_dot(source: &str) -> Result<String, String> {
+    let mut parser = DotParser::new(source);
+    let graph = parser.process()?;
+    let mut builder = GraphBuilder::new();
+    builder.visit_graph(&graph);
+    let mut visual = builder.get();
+    let mut svg = SVGWriter::new();
+    visual.do_it(false, false, false, &mut svg);
+    Ok(svg.finalize())
+}
+
+/// Rendered diagrams kept across renders. Documents are re-rendered on
+/// every save and theme change, and layout is the slow part, so unchanged
+/// fences are looked up by a hash of their source instead.
+fn cache() -> &'static Mutex<HashMap<String, Result<String, String>>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Result<String, String>>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn cached_render(kind: Kind, source: &str) -> Result<String, String> {
+    let mut hasher = Sha256::new();
+    hasher.update(kind.name());
+    hasher.update([0]);
+    hasher.update(source);
+    let key = format!("{:x}", hasher.finalize());
+
+    if let Some(hit) = cache().lock().ok().and_then(|cache| cache.get(&key).cloned()) {
+        return hit;
+    }
+
+    // The layout engines assume well-formed graphs and can panic on odd
+    // input; a broken diagram must not take the whole render down with it.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| kind.render(source)))
+        .unwrap_or_else(|_| Err(format!("the {} renderer failed on this input", kind.name())));
+
+    if let Ok(mut cache) = cache().lock() {
+        if cache.len() >= CACHE_LIMIT {
+            cache.clear();
+        }
+        cache.insert(key, result.clone());
+    }
+    result
+}
+
+fn error_box(kind: Kind, message: &str) -> String {
+    let mut escaped = Vec::new();
+    let _ = html::escape(&mut escaped, message.as_bytes());
+    format!(
+        "<div class=\"diagram-error\"><strong>Could not render {} diagram</strong><code>{}</code></div>",
+        kind.name(),
+        String::from_utf8_lossy(&escaped)
+    )
+}
+
+/// Drops the `<?xml ...?>` declaration some renderers emit, which has no
+/// meaning inside an HTML document.
+fn strip_prolog(svg: &str) -> &str {
+    svg.find("<svg").map_or(svg, |start| &svg[start..])
+}
+
+fn placeholder(index: usize) -> String {
+    format!("<div data-diagram=\"{}\"></div>", index)
+}
+
+/// Swaps diagram fences for placeholder figures and returns the rendered
+/// SVG (or error box) for each, in placeholder order.
+///
+/// The SVG goes in through [`splice`] once the HTML is formatted and
+/// sanitized, because the raw HTML sanitizer doesn't allow SVG.
+pub fn replace<'a>(root: &'a AstNode<'a>) -> Vec<String> {
+    let mut diagrams = Vec::new();
+    for node in root.descendants() {
+        let mut ast = node.data.borrow_mut();
+        let (kind, source) = match &ast.value {
+            NodeValue::CodeBlock(block) if block.fenced => match Kind::from_info(&block.info) {
+                Some(kind) => (kind, block.literal.clone()),
+                None => continue,
+            },
+            _ => continue,
+        };
+
+        let body = cached_render(kind, &source)
+            .map(|svg| strip_prolog(&svg).to_string())
+            .unwrap_or_else(|e| error_box(kind, &e));
+        let pos = ast.sourcepos;
+        ast.value = NodeValue::Raw(format!(
+            "<figure class=\"diagram diagram-{}\" data-sourcepos=\"{}:{}-{}:{}\">{}</figure>\n",
+            kind.name(),
+            pos.start.line,
+            pos.start.column,
+            pos.end.line,
+            pos.end.column,
+            placeholder(diagrams.len())
+        ));
+        diagrams.push(body);
+    }
+    diagrams
+}
+
+pub fn splice(mut html: String, diagrams: &[String]) -> String {
+    for (index, body) in diagrams.iter().enumerate() {
+        let marker = placeholder(index);
+        let filled = format!("<div data-diagram=\"{}\">{}</div>", index, body);
+        html = html.replacen(&marker, &filled, 1);
+    }
+    html
+}
@@ -0,0 +1,295 @@
+use comrak::{html, Arena};
+use std::path::Path;
+
+use super::outline::{self, Section};
+use super::review::{self, ReviewNote};
+use super::{highlight::CodeTheme, parse, render_with, Output, RenderProfile, Source, SourceBlock};
+
+/// The document stylesheet the app itself uses, so exports look the same as
+/// the preview.
+const STYLESHEET: &str = include_str!("../../../src/style.css");
+
+/// `style.css` switches to dark colors through this media query. Exports
+/// are pinned to the theme they were made with, because code colors are
+/// baked into the highlighted HTML.
+const DARK_QUERY: &str = "@media (prefers-color-scheme: dark)";
+
+const LAYOUT_CSS: &str = r#"
+.export-layout {
+    display: flex;
+    align-items: flex-start;
+}
+
+.export-layout .markdown-body {
+    flex: 1;
+    min-width: 0;
+}
+
+.export-outline {
+    position: sticky;
+    top: 0;
+    flex-shrink: 0;
+    width: 260px;
+    max-height: 100vh;
+    overflow-y: auto;
+    padding: 40px 16px;
+    border-right: 1px solid var(--border);
+    font-size: 13px;
+    line-height: 1.5;
+}
+
+.export-outline ul {
+    list-style: none;
+    margin: 0;
+    padding-left: 12px;
+}
+
+.export-outline > ul {
+    padding-left: 0;
+}
+
+.export-outline li + li {
+    margin-top: 0;
+}
+
+.export-outline a {
+    color: var(--text-muted);
+}
+
+@media (max-width: 800px), print {
+    .export-outline {
+        display: none;
+    }
+}
+
+.review-ref {
+    margin-left: 4px;
+    font-size: 11px;
+    font-weight: 600;
+}
+
+.review-ref a {
+    padding: 0 5px;
+    border-radius: 8px;
+    background: var(--link);
+    color: var(--bg);
+}
+
+.review-notes {
+    margin-top: 48px;
+    border-top: 1px solid var(--border);
+}
+
+.review-note {
+    margin-bottom: 12px;
+}
+
+.review-meta {
+    color: var(--text-muted);
+    font-size: 13px;
+}
+
+.review-status {
+    font-weight: 600;
+}
+
+.review-note.open .review-status {
+    color: #d1242f;
+}
+
+.review-note.resolved .review-status {
+    color: #1a7f37;
+}
+
+.review-note.resolved .review-text {
+    color: var(--text-muted);
+}
+"#;
+
+/// Elements that can take the comment marker as their first child. Blocks
+/// that only contain other blocks (lists, tables, quotes) pass the marker
+/// down to their first child, which starts on the same line.
+const MARKABLE_TAGS: [&str; 14] = [
+    "p", "h1", "h2", "h3", "h4", "h5", "h6", "li", "th", "td", "pre", "figure", "dt", "dd",
+];
+
+fn escape(text: &str) -> String {
+    let mut escaped = Vec::new();
+    let _ = html::escape(&mut escaped, text.as_bytes());
+    String::from_utf8_lossy(&escaped).into_owned()
+}
+
+fn stylesheet(theme: CodeTheme) -> String {
+    let media = match theme {
+        CodeTheme::Light => "@media not all",
+        CodeTheme::Dark => "@media all",
+    };
+    STYLESHEET.replace(DARK_QUERY, media)
+}
+
+/// Inserts `marker` at the start of the first markable element whose
+/// source starts on `line`. Returns false if there is none.
+fn insert_marker(html: &mut String, line: usize, marker: &str) -> bool {
+    let needle = format!("data-sourcepos=\"{}:", line);
+    let mut from = 0;
+    while let Some(found) = html[from..].find(&needle) {
+        let at = from + found;
+        from = at + needle.len();
+        let Some(open) = html[..at].rfind('<') else {
+            continue;
+        };
+        let tag: String = html[open + 1..at]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+        if !MARKABLE_TAGS.contains(&tag.as_str()) {
+            continue;
+        }
+        let Some(end) = html[at..].find('>') else {
+            return false;
+        };
+        html.insert_str(at + end + 1, marker);
+        return true;
+    }
+    false
+}
+
+/// Marks commented blocks with their note numbers and returns which notes
+/// got a marker.
+fn mark_commented_blocks(html: &mut String, notes: &[ReviewNote]) -> Vec<bool> {
+    let mut placed = vec![false; notes.len()];
+    let mut index = 0;
+    while index < notes.len() {
+        let Some(line) = notes[index].line else {
+            index += 1;
+            continue;
+        };
+        // Notes are sorted by line, so notes on the same block are adjacent
+        // and share one insertion.
+        let same_line = notes[index..]
+            .iter()
+            .take_while(|note| note.line == Some(line))
+            .count();
+        let marker: String = (index..index + same_line)
+            .map(|i| {
+                format!(
+                    "<sup class=\"review-ref\" id=\"review-ref-{n}\"><a href=\"#review-note-{n}\">{n}</a></sup>",
+                    n = i + 1
+                )
+            })
+            .collect();
+        if insert_marker(html, line, &marker) {
+            placed[index..index + same_line].fill(true);
+        }
+        index += same_line;
+    }
+    placed
+}
+
+fn write_endnotes(notes: &[ReviewNote], placed: &[bool], blocks: &[SourceBlock]) -> String {
+    let mut section = String::from(
+        "<section class=\"review-notes\" id=\"review-comments\">\n<h2>Review comments</h2>\n<ol>\n",
+    );
+    for (index, note) in notes.iter().enumerate() {
+        let state = if note.resolved { "resolved" } else { "open" };
+        let mut meta = format!(
+            "<span class=\"review-status\">{}</span> · {}",
+            note.status(),
+            review::format_timestamp(note.timestamp)
+        );
+        if let Some(line) = note.line {
+            let lines = review::describe_lines(review::block_lines(blocks, line));
+            if placed[index] {
+                meta.push_str(&format!(" · <a href=\"#review-ref-{}\">{}</a>", index + 1, lines));
+            } else {
+                meta.push_str(&format!(" · {}", lines));
+            }
+        }
+        section.push_str(&format!(
+            "<li id=\"review-note-{}\" class=\"review-note {}\"><div class=\"review-meta\">{}</div><div class=\"review-text\">{}</div></li>\n",
+            index + 1,
+            state,
+            meta,
+            escape(note.text.trim()).replace('\n', "<br>")
+        ));
+    }
+    section.push_str("</ol>\n</section>\n");
+    section
+}
+
+fn write_sections(sections: &[Section], nav: &mut String) {
+    if sections.is_empty() {
+        return;
+    }
+    nav.push_str("<ul>");
+    for section in sections {
+        nav.push_str(&format!(
+            "<li><a href=\"#{}\">{}</a>",
+            escape(&section.heading.slug),
+            escape(&section.heading.text)
+        ));
+        write_sections(&section.children, nav);
+        nav.push_str("</li>");
+    }
+    nav.push_str("</ul>");
+}
+
+/// Renders a document into a single HTML file with the stylesheet inlined,
+/// local images embedded as `data:` URIs and the heading outline as a
+/// sidebar nav. Review `notes`, if any, are linked from their blocks and
+/// listed at the end; they're expected in [`review::sort`] order.
+pub fn standalone_html(
+    source: &Source,
+    profile: &RenderProfile,
+    theme: CodeTheme,
+    document: &Path,
+    notes: &[ReviewNote],
+) -> String {
+    // The outline links to headings by slug, so heading ids are needed
+    // whatever the profile says.
+    let profile = RenderProfile {
+        header_ids: true,
+        ..profile.clone()
+    };
+    let rendered = render_with(source, &profile, theme, Some(document), Output::Standalone);
+    let mut body = rendered.html;
+    if !notes.is_empty() {
+        let placed = mark_commented_blocks(&mut body, notes);
+        body.push_str(&write_endnotes(notes, &placed, &rendered.blocks));
+    }
+
+    let arena = Arena::new();
+    let root = parse(&arena, &source.markdown, &profile);
+    let outline = outline::build(root, &source.markdown, &[]);
+
+    let title = outline
+        .sections
+        .first()
+        .map(|section| section.heading.text.clone())
+        .or_else(|| document.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .unwrap_or_default();
+
+    let mut nav = String::new();
+    if !outline.sections.is_empty() {
+        nav.push_str("<nav class=\"export-outline\">");
+        write_sections(&outline.sections, &mut nav);
+        nav.push_str("</nav>\n");
+    }
+
+    let color_scheme = match theme {
+        CodeTheme::Light => "light",
+        CodeTheme::Dark => "dark",
+    };
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <meta name=\"color-scheme\" content=\"{color_scheme}\">\n\
+         <title>{title}</title>\n<style>\n{css}\n{layout}</style>\n</head>\n<body>\n\
+         <div class=\"export-layout\">\n{nav}<main class=\"markdown-body\">\n{body}</main>\n</div>\n\
+         </body>\n</html>\n",
+        title = escape(&title),
+        css = stylesheet(theme),
+        layout = LAYOUT_CSS,
+    )
+}
@@ -0,0 +1,51 @@
+//! Source code and plain text files.
+//!
+//! A file becomes a single fenced block in its own language, highlighted
+//! like any other fence. [`Source::line_numbers`] has the highlighter give
+//! every line a number and an `L<n>` anchor, so `#L10-L20` links can point
+//! into it. File line `n` is line `n + 1` of the converted document, below
+//! the opening fence.
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use super::{fence, Source};
+use crate::markdown::highlight;
+
+/// Opened as plain text although no grammar claims them.
+const PLAIN_TEXT: &[&str] = &["txt", "text", "log"];
+
+/// Left to the system: browsers and image viewers show these better.
+const EXCLUDED: &[&str] = &["html", "htm", "xhtml", "svg"];
+
+/// Every extension or file name (`makefile`) a grammar is registered for.
+pub fn extensions() -> &'static [&'static str] {
+    static EXTENSIONS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    EXTENSIONS.get_or_init(|| {
+        let mut extensions: Vec<&'static str> = highlight::grammar_extensions()
+            .filter(|ext| *ext == ext.to_lowercase() && !EXCLUDED.contains(ext))
+            .chain(PLAIN_TEXT.iter().copied())
+            .collect();
+        extensions.sort_unstable();
+        extensions.dedup();
+        extensions
+    })
+}
+
+/// The name a file's grammar is looked up by: its extension, or its whole
+/// name when it has none.
+pub fn language(path: &Path) -> Option<String> {
+    path.extension()
+        .or_else(|| path.file_name())
+        .and_then(|name| name.to_str())
+        .map(str::to_lowercase)
+}
+
+pub fn convert(content: &str, document: Option<&Path>) -> Source {
+    let language = document.and_then(language).unwrap_or_default();
+    Source {
+        markdown: format!("{}\n", fence(content, &language)),
+        line_numbers: true,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,260 @@
+//! Document formats the viewer opens besides markdown.
+//!
+//! Every format is converted into markdown before parsing, so rendering,
+//! headings, the outline, watching and comments work the same for all of
+//! them. Line numbers in source positions and comments are counted in the
+//! converted document; converters keep blocks on their original lines
+//! where markdown has a one-line equivalent.
+//!
+//! Supporting another format takes a [`Format`] variant, its extensions
+//! and a converter.
+
+pub mod code;
+pub mod notebook;
+pub mod org;
+pub mod rst;
+pub mod table;
+pub mod tree;
+
+use comrak::html;
+use comrak::nodes::{AstNode, NodeValue};
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Notebook,
+    Org,
+    Rst,
+    Csv,
+    Tsv,
+    Json,
+    Yaml,
+    Toml,
+    /// Any other text file a grammar exists for. Last, so formats with a
+    /// view of their own win.
+    Code,
+}
+
+/// A format as offered in the open dialog.
+#[derive(Debug, Serialize, Clone)]
+pub struct FormatInfo {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+}
+
+impl Format {
+    pub const ALL: [Format; 10] = [
+        Format::Markdown,
+        Format::Notebook,
+        Format::Org,
+        Format::Rst,
+        Format::Csv,
+        Format::Tsv,
+        Format::Json,
+        Format::Yaml,
+        Format::Toml,
+        Format::Code,
+    ];
+
+    /// Format for a file by extension. Anything unknown is read as
+    /// markdown, like files opened before other formats existed.
+    pub fn from_path(path: &Path) -> Self {
+        Self::detect(path).unwrap_or(Format::Markdown)
+    }
+
+    /// Format for a file by extension, if it's one the app opens. Files
+    /// without one are matched by name (`Makefile`).
+    pub fn detect(path: &Path) -> Option<Self> {
+        let ext = code::language(path)?;
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extensions().contains(&ext.as_str()))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Markdown => "Markdown",
+            Format::Notebook => "Jupyter Notebook",
+            Format::Org => "Org",
+            Format::Rst => "reStructuredText",
+            Format::Csv => "CSV",
+            Format::Tsv => "TSV",
+            Format::Json => "JSON",
+            Format::Yaml => "YAML",
+            Format::Toml => "TOML",
+            Format::Code => "Source code",
+        }
+    }
+
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Format::Markdown => &["md", "markdown", "mdown", "mkd", "mkdn"],
+            Format::Notebook => &["ipynb"],
+            Format::Org => &["org"],
+            Format::Rst => &["rst", "rest"],
+            Format::Csv => &["csv"],
+            Format::Tsv => &["tsv", "tab"],
+            Format::Json => &["json"],
+            Format::Yaml => &["yaml", "yml"],
+            Format::Toml => &["toml"],
+            Format::Code => code::extensions(),
+        }
+    }
+
+    /// Field delimiter for formats shown as a table.
+    pub fn delimiter(self) -> Option<u8> {
+        match self {
+            Format::Csv => Some(b','),
+            Format::Tsv => Some(b'\t'),
+            _ => None,
+        }
+    }
+
+    pub fn info(self) -> FormatInfo {
+        FormatInfo {
+            name: self.name(),
+            extensions: self.extensions(),
+        }
+    }
+
+    /// Converts `content` to markdown. `document` is the file it was read
+    /// from, which source code needs to pick a grammar.
+    pub fn convert(self, content: &str, document: Option<&Path>) -> Result<Source, String> {
+        match self {
+            Format::Markdown => Ok(Source {
+                markdown: content.to_string(),
+                ..Default::default()
+            }),
+            Format::Notebook => notebook::convert(content),
+            Format::Org => Ok(Source {
+                markdown: org::to_markdown(content),
+                ..Default::default()
+            }),
+            Format::Rst => Ok(Source {
+                markdown: rst::to_markdown(content),
+                ..Default::default()
+            }),
+            Format::Csv => Ok(table::convert(content, b',')),
+            Format::Tsv => Ok(table::convert(content, b'\t')),
+            Format::Json => Ok(tree::convert(content, "json", tree::json)),
+            Format::Yaml => Ok(tree::convert(content, "yaml", tree::yaml)),
+            Format::Toml => Ok(tree::convert(content, "toml", tree::toml)),
+            Format::Code => Ok(code::convert(content, document)),
+        }
+    }
+}
+
+/// Whether the app opens `path` itself rather than handing it to the
+/// system.
+pub fn is_document(path: &Path) -> bool {
+    Format::detect(path).is_some()
+}
+
+/// Walks the files under `folder` the way documents are listed from a
+/// folder, by the workspace tree and the link checker alike: hidden files
+/// are skipped, and `.gitignore` files apply even outside a git repository.
+pub fn walk(folder: &Path) -> ignore::Walk {
+    ignore::WalkBuilder::new(folder).require_git(false).build()
+}
+
+/// Converts `content` by the format of the file it was read from. Content
+/// without a path is markdown.
+pub fn load(content: &str, document: Option<&Path>) -> Result<Source, String> {
+    document
+        .map_or(Format::Markdown, Format::from_path)
+        .convert(content, document)
+}
+
+/// Read-only HTML that has no markdown form, such as notebook outputs,
+/// and the line of the placeholder block it replaces.
+#[derive(Debug, Clone)]
+pub struct Embed {
+    pub line: usize,
+    pub class: &'static str,
+    pub html: String,
+}
+
+/// A document converted to markdown, plus the HTML to embed in it.
+#[derive(Debug, Clone, Default)]
+pub struct Source {
+    pub markdown: String,
+    pub embeds: Vec<Embed>,
+    /// Number the lines of code blocks, for documents that are code.
+    pub line_numbers: bool,
+}
+
+impl Source {
+    fn next_line(&self) -> usize {
+        self.markdown.matches('\n').count() + 1
+    }
+
+    /// Appends a block followed by a blank line, so blocks never run into
+    /// each other.
+    pub fn push_block(&mut self, block: &str) {
+        self.markdown.push_str(block.trim_end_matches(['\n', '\r']));
+        self.markdown.push_str("\n\n");
+    }
+
+    /// Appends a placeholder block that [`embed`] replaces with `html`.
+    pub fn push_embed(&mut self, class: &'static str, html: String) {
+        let line = self.next_line();
+        self.push_block(&placeholder(self.embeds.len()));
+        self.embeds.push(Embed { line, class, html });
+    }
+}
+
+fn placeholder(index: usize) -> String {
+    format!("<div data-embed=\"{}\"></div>", index)
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = Vec::new();
+    let _ = html::escape(&mut escaped, text.as_bytes());
+    String::from_utf8_lossy(&escaped).into_owned()
+}
+
+/// A code fence long enough that no backtick run in the code closes it
+/// early.
+pub fn fence(code: &str, language: &str) -> String {
+    let longest = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let marker = "`".repeat(longest.max(2) + 1);
+    format!(
+        "{}{}\n{}\n{}",
+        marker,
+        language,
+        code.trim_end_matches(['\n', '\r']),
+        marker
+    )
+}
+
+/// Swaps the placeholder blocks in a parsed document for their embeds.
+/// They go in as raw HTML, so they show up whether or not raw HTML
+/// passthrough is on; converters sanitize anything they didn't write.
+pub fn embed<'a>(root: &'a AstNode<'a>, embeds: &[Embed]) {
+    if embeds.is_empty() {
+        return;
+    }
+    for node in root.descendants() {
+        let mut ast = node.data.borrow_mut();
+        let NodeValue::HtmlBlock(block) = &ast.value else {
+            continue;
+        };
+        let pos = ast.sourcepos;
+        let Some((index, embed)) = embeds
+            .iter()
+            .enumerate()
+            .find(|(_, embed)| embed.line == pos.start.line)
+        else {
+            continue;
+        };
+        if block.literal.trim() != placeholder(index) {
+            continue;
+        }
+        ast.value = NodeValue::Raw(format!(
+            "<div class=\"{}\" data-sourcepos=\"{}:{}-{}:{}\">{}</div>\n",
+            embed.class, pos.start.line, pos.start.column, pos.end.line, pos.end.column, embed.html
+        ));
+    }
+}
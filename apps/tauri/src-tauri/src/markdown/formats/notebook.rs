@@ -0,0 +1,312 @@
+//! Jupyter notebooks (`.ipynb`).
+//!
+//! Markdown cells are taken as they are, code cells become fences in the
+//! kernel's language and stored outputs are embedded as read-only HTML
+//! after the cell that produced them. Line numbers are counted in the
+//! converted document.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use super::{escape, fence, Source};
+use crate::markdown::sanitize;
+
+/// Kernel language for notebooks whose metadata doesn't name one.
+const DEFAULT_LANGUAGE: &str = "python";
+
+/// Rich output types shown, most preferred first (Jupyter's own order,
+/// minus the ones that need a kernel or script to display).
+const DISPLAY_ORDER: &[&str] = &[
+    "text/html",
+    "image/svg+xml",
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "text/plain",
+];
+
+#[derive(Deserialize)]
+struct Notebook {
+    nbformat: u32,
+    #[serde(default)]
+    metadata: Metadata,
+    #[serde(default)]
+    cells: Vec<Cell>,
+}
+
+#[derive(Deserialize, Default)]
+struct Metadata {
+    language_info: Option<LanguageInfo>,
+    kernelspec: Option<KernelSpec>,
+}
+
+#[derive(Deserialize)]
+struct LanguageInfo {
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct KernelSpec {
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Cell {
+    cell_type: String,
+    #[serde(default)]
+    source: MultilineString,
+    #[serde(default)]
+    outputs: Vec<Output>,
+    /// Files pasted into markdown cells, by name and MIME type.
+    #[serde(default)]
+    attachments: Map<String, Value>,
+}
+
+/// nbformat stores text either as one string or as a list of lines.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MultilineString {
+    Text(String),
+    Lines(Vec<String>),
+}
+
+impl Default for MultilineString {
+    fn default() -> Self {
+        MultilineString::Text(String::new())
+    }
+}
+
+impl MultilineString {
+    fn text(&self) -> String {
+        match self {
+            MultilineString::Text(text) => text.clone(),
+            MultilineString::Lines(lines) => lines.concat(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "output_type", rename_all = "snake_case")]
+enum Output {
+    Stream {
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        text: MultilineString,
+    },
+    ExecuteResult {
+        #[serde(default)]
+        data: Map<String, Value>,
+        execution_count: Option<u32>,
+    },
+    DisplayData {
+        #[serde(default)]
+        data: Map<String, Value>,
+    },
+    Error {
+        #[serde(default)]
+        ename: String,
+        #[serde(default)]
+        evalue: String,
+        #[serde(default)]
+        traceback: Vec<String>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Converts notebook JSON (nbformat 4) into markdown.
+pub fn convert(json: &str) -> Result<Source, String> {
+    let notebook: Notebook =
+        serde_json::from_str(json).map_err(|e| format!("Invalid notebook: {}", e))?;
+    if notebook.nbformat < 4 {
+        return Err(format!(
+            "Notebook format {} is not supported; save it with Jupyter 4 or later",
+            notebook.nbformat
+        ));
+    }
+
+    let language = notebook
+        .metadata
+        .language_info
+        .and_then(|info| info.name)
+        .or_else(|| notebook.metadata.kernelspec.and_then(|spec| spec.language))
+        .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string());
+
+    let mut source = Source::default();
+    for cell in &notebook.cells {
+        let text = cell.source.text();
+        match cell.cell_type.as_str() {
+            "markdown" => {
+                let text = with_attachments(&text, &cell.attachments);
+                if !text.trim().is_empty() {
+                    source.push_block(&text);
+                }
+            }
+            "code" => {
+                if !text.trim().is_empty() {
+                    source.push_block(&fence(&text, &language));
+                }
+                let html = render_outputs(&cell.outputs);
+                if !html.is_empty() {
+                    source.push_embed("nb-outputs", html);
+                }
+            }
+            // Raw cells hold text for nbconvert, shown as Jupyter shows
+            // them: unformatted.
+            _ => {
+                if !text.trim().is_empty() {
+                    source.push_block(&fence(&text, "text"));
+                }
+            }
+        }
+    }
+    Ok(source)
+}
+
+/// Points `attachment:name` image sources at `data:` URIs built from the
+/// cell's attachments.
+fn with_attachments(text: &str, attachments: &Map<String, Value>) -> String {
+    let mut text = text.to_string();
+    for (name, bundle) in attachments {
+        let Some((mime, data)) = bundle
+            .as_object()
+            .and_then(|bundle| bundle.iter().find(|(mime, _)| mime.starts_with("image/")))
+        else {
+            continue;
+        };
+        let Some(data) = mime_text(data) else {
+            continue;
+        };
+        let Some(uri) = image_uri(mime, &data) else {
+            continue;
+        };
+        text = text.replace(&format!("attachment:{}", name), &uri);
+    }
+    text
+}
+
+/// MIME bundle values are strings or lists of lines, like cell sources.
+fn mime_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(lines) => Some(lines.iter().filter_map(Value::as_str).collect()),
+        _ => None,
+    }
+}
+
+/// A `data:` URI for base64 image data from the notebook. The data is
+/// decoded and encoded again so nothing but base64 ends up in the URI.
+/// `None` if it isn't base64, or `mime` isn't a plain `image/subtype`.
+fn image_uri(mime: &str, data: &str) -> Option<String> {
+    let subtype = mime.strip_prefix("image/")?;
+    let plain = !subtype.is_empty()
+        && subtype
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"+-.".contains(&b));
+    if !plain {
+        return None;
+    }
+    let data: String = data.chars().filter(|c| !c.is_whitespace()).collect();
+    let bytes = STANDARD.decode(data).ok()?;
+    Some(format!("data:{};base64,{}", mime, STANDARD.encode(bytes)))
+}
+
+/// Drops the terminal color codes that tracebacks and some library output
+/// are full of.
+fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            plain.push(c);
+            continue;
+        }
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    plain
+}
+
+fn text_output(text: &str, class: &str) -> String {
+    format!(
+        "<div class=\"nb-text {}\">{}</div>",
+        class,
+        escape(strip_ansi(text).trim_end_matches('\n'))
+    )
+}
+
+/// The preferred representation of a rich output.
+fn render_data(data: &Map<String, Value>) -> Option<String> {
+    let (mime, value) = DISPLAY_ORDER
+        .iter()
+        .find_map(|mime| data.get(*mime).map(|value| (*mime, value)))?;
+    let text = mime_text(value)?;
+    Some(match mime {
+        "text/html" => format!("<div class=\"nb-html\">{}</div>", sanitize::clean(&text)),
+        "text/plain" => text_output(&text, "nb-result"),
+        // SVG goes in as an image, which the sanitizer allows and which
+        // keeps any scripts in it from running.
+        "image/svg+xml" => format!(
+            "<img class=\"nb-image\" alt=\"Output\" src=\"data:image/svg+xml;base64,{}\">",
+            STANDARD.encode(text)
+        ),
+        _ => format!(
+            "<img class=\"nb-image\" alt=\"Output\" src=\"{}\">",
+            image_uri(mime, &text)?
+        ),
+    })
+}
+
+fn render_outputs(outputs: &[Output]) -> String {
+    let mut html = String::new();
+    for output in outputs {
+        match output {
+            Output::Stream { name, text } => {
+                let class = if name == "stderr" {
+                    "nb-stderr"
+                } else {
+                    "nb-stdout"
+                };
+                html.push_str(&text_output(&text.text(), class));
+            }
+            Output::ExecuteResult {
+                data,
+                execution_count,
+            } => {
+                if let Some(body) = render_data(data) {
+                    if let Some(count) = execution_count {
+                        html.push_str(&format!("<div class=\"nb-prompt\">Out [{}]:</div>", count));
+                    }
+                    html.push_str(&body);
+                }
+            }
+            Output::DisplayData { data } => {
+                if let Some(body) = render_data(data) {
+                    html.push_str(&body);
+                }
+            }
+            Output::Error {
+                ename,
+                evalue,
+                traceback,
+            } => {
+                let text = if traceback.is_empty() {
+                    format!("{}: {}", ename, evalue)
+                } else {
+                    traceback.join("\n")
+                };
+                html.push_str(&text_output(&text, "nb-error"));
+            }
+            Output::Other => {}
+        }
+    }
+    html
+}
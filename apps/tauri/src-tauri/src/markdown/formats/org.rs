@@ -0,0 +1,369 @@
+//! Org-mode documents.
+//!
+//! Converted line for line: headlines, lists, tables, blocks and inline
+//! markup map to their markdown equivalents, and lines with no visible
+//! output (keywords, comments, drawers) become blank lines.
+
+use comrak::Anchorizer;
+
+/// Extensions shown as images when a link has no description.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "svg", "webp"];
+
+enum Block {
+    /// `#+begin_src` and `#+begin_example`, copied verbatim.
+    Code {
+        end: &'static str,
+    },
+    Quote,
+    /// `:PROPERTIES:` and other drawers, hidden.
+    Drawer,
+}
+
+/// A list item that lines indented past its bullet still belong to.
+struct OpenItem {
+    /// Indentation of the bullet in the org source.
+    bullet: usize,
+    /// Column the item's content starts at in the markdown.
+    content: usize,
+}
+
+pub fn to_markdown(content: &str) -> String {
+    let mut lines = Vec::new();
+    let mut block = None;
+    let mut items = Vec::new();
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        let keyword = trimmed.to_ascii_lowercase();
+
+        match &block {
+            Some(Block::Code { end }) => {
+                if keyword.starts_with(end) {
+                    lines.push("```".to_string());
+                    block = None;
+                } else {
+                    lines.push(line.to_string());
+                }
+                continue;
+            }
+            Some(Block::Quote) if keyword.starts_with("#+end_quote") => {
+                lines.push(String::new());
+                block = None;
+                continue;
+            }
+            Some(Block::Drawer) => {
+                if keyword == ":end:" {
+                    block = None;
+                }
+                lines.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+
+        let converted = if let Some(language) = keyword.strip_prefix("#+begin_src") {
+            // The header may carry switches after the language (`-n :exports both`).
+            let language = language.split_whitespace().next().unwrap_or("");
+            block = Some(Block::Code { end: "#+end_src" });
+            format!("```{}", language)
+        } else if keyword.starts_with("#+begin_example") {
+            block = Some(Block::Code {
+                end: "#+end_example",
+            });
+            "```text".to_string()
+        } else if keyword.starts_with("#+begin_quote") {
+            block = Some(Block::Quote);
+            String::new()
+        } else if let Some(title) = keyword
+            .starts_with("#+title:")
+            .then(|| trimmed["#+title:".len()..].trim())
+        {
+            format!("# {}", inline(title))
+        } else if keyword.starts_with("#+") || trimmed == "#" || trimmed.starts_with("# ") {
+            // Other keywords (`#+author:`, `#+begin_center`…) and comments.
+            String::new()
+        } else if is_drawer_start(trimmed) && block.is_none() {
+            block = Some(Block::Drawer);
+            String::new()
+        } else if let Some(heading) = headline(line) {
+            items.clear();
+            heading
+        } else if trimmed.len() >= 5 && trimmed.chars().all(|c| c == '-') {
+            "---".to_string()
+        } else if trimmed.starts_with('|') {
+            table_row(trimmed)
+        } else if let Some(example) = trimmed
+            .strip_prefix(": ")
+            .or((trimmed == ":").then_some(""))
+        {
+            // Fixed-width lines.
+            if example.is_empty() {
+                String::new()
+            } else {
+                code_span(example)
+            }
+        } else if let Some(item) = list_item(line, &mut items) {
+            item
+        } else {
+            paragraph(line, &mut items)
+        };
+
+        if matches!(block, Some(Block::Quote)) && !converted.is_empty() {
+            lines.push(format!("> {}", converted.trim_start()));
+        } else {
+            lines.push(converted);
+        }
+    }
+
+    // An unterminated source block still gets closed.
+    if matches!(block, Some(Block::Code { .. })) {
+        lines.push("```".to_string());
+    }
+
+    let mut markdown = lines.join("\n");
+    markdown.push('\n');
+    markdown
+}
+
+fn is_drawer_start(line: &str) -> bool {
+    line.len() > 2
+        && line.starts_with(':')
+        && line.ends_with(':')
+        && line[1..line.len() - 1]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// `** TODO [#A] Title :tag:` to `## TODO Title`.
+fn headline(line: &str) -> Option<String> {
+    let stars = line.chars().take_while(|&c| c == '*').count();
+    if stars == 0 || !line[stars..].starts_with(' ') {
+        return None;
+    }
+    let mut title = line[stars..].trim();
+
+    // Trailing tags.
+    if let Some(start) = title.rfind(char::is_whitespace) {
+        let tags = &title[start + 1..];
+        if tags.len() > 2 && tags.starts_with(':') && tags.ends_with(':') && !tags.contains(' ') {
+            title = title[..start].trim_end();
+        }
+    } else if title.len() > 2 && title.starts_with(':') && title.ends_with(':') {
+        title = "";
+    }
+
+    let title = title
+        .replacen("[#A] ", "", 1)
+        .replacen("[#B] ", "", 1)
+        .replacen("[#C] ", "", 1);
+    Some(format!("{} {}", "#".repeat(stars.min(6)), inline(&title)))
+}
+
+/// `|---+---|` separators become markdown delimiter rows.
+fn table_row(line: &str) -> String {
+    if line.starts_with("|-") {
+        return line
+            .chars()
+            .map(|c| if c == '+' { '|' } else { c })
+            .collect();
+    }
+    inline(line)
+}
+
+/// How far a line is indented in the org source.
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Closes the items a line indented by `indent` doesn't belong to, and
+/// returns the markdown indentation that keeps it in the rest.
+fn nest(items: &mut Vec<OpenItem>, indent: usize) -> usize {
+    while items.last().is_some_and(|item| item.bullet >= indent) {
+        items.pop();
+    }
+    items.last().map_or(0, |item| item.content)
+}
+
+/// Text lines. Org indentation is only kept as far as it puts a line in a
+/// list item, since four spaces or more would make an indented code block.
+fn paragraph(line: &str, items: &mut Vec<OpenItem>) -> String {
+    let text = line.trim_start();
+    if text.is_empty() {
+        return String::new();
+    }
+    let indent = nest(items, indentation(line));
+    format!("{}{}", " ".repeat(indent), inline(text))
+}
+
+/// Bullets (`-`, `+`, indented `*`), numbered items (`1.`, `1)`),
+/// checkboxes and `term :: description` items. Nested items are indented
+/// to the content of their parent in the markdown, whatever their
+/// indentation in the org source.
+fn list_item(line: &str, items: &mut Vec<OpenItem>) -> Option<String> {
+    let indent = indentation(line);
+    let rest = &line[indent..];
+
+    let (marker, body) = if let Some(body) = rest
+        .strip_prefix("- ")
+        .or_else(|| rest.strip_prefix("+ "))
+        .or_else(|| (indent > 0).then(|| rest.strip_prefix("* ")).flatten())
+    {
+        ("-".to_string(), body)
+    } else {
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        let after = &rest[digits..];
+        if digits == 0 || !(after.starts_with(". ") || after.starts_with(") ")) {
+            return None;
+        }
+        (format!("{}.", &rest[..digits]), &after[2..])
+    };
+
+    let (checkbox, body) = match body.get(..4) {
+        Some("[ ] ") | Some("[-] ") => ("[ ] ", &body[4..]),
+        Some("[X] ") | Some("[x] ") => ("[x] ", &body[4..]),
+        _ => ("", body),
+    };
+
+    let body = match body.split_once(" :: ") {
+        Some((term, description)) => format!("**{}**: {}", inline(term), inline(description)),
+        None => inline(body),
+    };
+
+    let column = nest(items, indent);
+    items.push(OpenItem {
+        bullet: indent,
+        content: column + marker.len() + 1,
+    });
+    Some(format!(
+        "{}{} {}{}",
+        " ".repeat(column),
+        marker,
+        checkbox,
+        body
+    ))
+}
+
+fn code_span(text: &str) -> String {
+    if text.contains('`') {
+        format!("`` {} ``", text)
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+/// `[[target][description]]` and `[[target]]` links.
+fn link(target: &str, description: Option<&str>) -> String {
+    let target = target.strip_prefix("file:").unwrap_or(target);
+    if let Some(heading) = target.strip_prefix('*') {
+        let slug = Anchorizer::new().anchorize(heading.to_string());
+        return format!("[{}](#{})", inline(description.unwrap_or(heading)), slug);
+    }
+
+    let is_image = target
+        .rsplit_once('.')
+        .is_some_and(|(_, ext)| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+    let target = target.replace(' ', "%20");
+    match description {
+        Some(description) => format!("[{}]({})", inline(description), target),
+        None if is_image => format!("![]({})", target),
+        None => format!("[{}]({})", target, target),
+    }
+}
+
+/// Org allows emphasis to open after these, besides whitespace.
+fn opens_after(c: char) -> bool {
+    c.is_whitespace() || "-({'\"".contains(c)
+}
+
+/// ...and to close before these.
+fn closes_before(c: char) -> bool {
+    c.is_whitespace() || "-.,;:!?'\")}[\\".contains(c)
+}
+
+/// Converts org inline markup: `*bold*`, `/italic/`, `=verbatim=`,
+/// `~code~`, `+strike+`, `_underline_` (shown plain) and links.
+fn inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '[' && chars.get(i + 1) == Some(&'[') {
+            let rest: String = chars[i + 2..].iter().collect();
+            if let Some(end) = rest.find("]]") {
+                let inner = &rest[..end];
+                out.push_str(&match inner.split_once("][") {
+                    Some((target, description)) => link(target, Some(description)),
+                    None => link(inner, None),
+                });
+                i += 2 + inner.chars().count() + 2;
+                continue;
+            }
+        }
+
+        let opens = i == 0 || opens_after(chars[i - 1]);
+        if opens && "*/=~+_".contains(c) {
+            let starts_word = chars.get(i + 1).is_some_and(|next| !next.is_whitespace());
+            let close = (i + 2..chars.len()).find(|&j| {
+                chars[j] == c
+                    && !chars[j - 1].is_whitespace()
+                    && chars.get(j + 1).is_none_or(|&next| closes_before(next))
+            });
+            if let (true, Some(close)) = (starts_word, close) {
+                let inner: String = chars[i + 1..close].iter().collect();
+                out.push_str(&match c {
+                    '*' => format!("**{}**", inline(&inner)),
+                    '/' => format!("*{}*", inline(&inner)),
+                    '+' => format!("~~{}~~", inline(&inner)),
+                    '_' => inline(&inner),
+                    _ => code_span(&inner),
+                });
+                i = close + 1;
+                continue;
+            }
+        }
+
+        // Org has no use for these outside markup; markdown would.
+        if "*_`~".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indented_paragraphs_are_not_code() {
+        assert_eq!(
+            to_markdown("Text\n\n      indented *bold*\n"),
+            "Text\n\nindented **bold**\n"
+        );
+    }
+
+    #[test]
+    fn nested_items_follow_their_parent() {
+        let org = "- a\n      - b\n            1. c\n               more of c\n      - d\n  more of a\n- e\n";
+        let markdown = "- a\n  - b\n    1. c\n       more of c\n  - d\n  more of a\n- e\n";
+        assert_eq!(to_markdown(org), markdown);
+    }
+
+    #[test]
+    fn text_after_a_list_ends_it() {
+        assert_eq!(
+            to_markdown("- a\n    - b\n\nText\n"),
+            "- a\n  - b\n\nText\n"
+        );
+    }
+
+    #[test]
+    fn headlines_end_lists() {
+        assert_eq!(to_markdown("- a\n* H\n   text\n"), "- a\n# H\ntext\n");
+    }
+}
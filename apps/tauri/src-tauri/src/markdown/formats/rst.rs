@@ -0,0 +1,756 @@
+//! reStructuredText documents.
+//!
+//! Section titles, lists, literal and code blocks, admonitions, images,
+//! tables and inline markup are converted to their markdown equivalents,
+//! mostly on the lines they came from. Directives with nothing to show in
+//! a viewer (`toctree`, comments, link targets) become blank lines.
+
+use comrak::Anchorizer;
+use std::collections::HashMap;
+
+use super::fence;
+
+/// Admonition directives and the alert each is shown as.
+const ADMONITIONS: &[(&str, &str)] = &[
+    ("note", "NOTE"),
+    ("seealso", "NOTE"),
+    ("admonition", "NOTE"),
+    ("tip", "TIP"),
+    ("hint", "TIP"),
+    ("important", "IMPORTANT"),
+    ("warning", "WARNING"),
+    ("attention", "WARNING"),
+    ("caution", "CAUTION"),
+    ("danger", "CAUTION"),
+    ("error", "CAUTION"),
+];
+
+const CODE_DIRECTIVES: &[&str] = &["code", "code-block", "sourcecode"];
+
+pub fn to_markdown(content: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut converter = Converter {
+        targets: targets(&lines),
+        lines,
+        out: Vec::new(),
+        styles: Vec::new(),
+        list: None,
+    };
+    let mut i = 0;
+    while i < converter.lines.len() {
+        i = converter.step(i);
+    }
+    let mut markdown = converter.out.join("\n");
+    markdown.push('\n');
+    markdown
+}
+
+/// Named hyperlink targets (`.. _name: url`), by lowercased name.
+fn targets(lines: &[&str]) -> HashMap<String, String> {
+    lines
+        .iter()
+        .filter_map(|line| {
+            let rest = line.trim().strip_prefix(".. _")?;
+            let (name, url) = rest.split_once(": ")?;
+            let name = name.trim_matches('`');
+            Some((name.to_lowercase(), url.trim().to_string()))
+        })
+        .collect()
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+/// Leading spaces and tabs, in bytes. Other whitespace, like a no-break
+/// space, doesn't indent, so the indent of any line can be sliced off any
+/// other that's indented at least as far.
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+/// A line made of one punctuation character repeated: a section
+/// adornment or a transition.
+fn adornment(line: &str) -> Option<char> {
+    let trimmed = line.trim_end();
+    let first = trimmed.chars().next()?;
+    (trimmed.len() >= 2
+        && !line.starts_with(char::is_whitespace)
+        && first.is_ascii_punctuation()
+        && trimmed.chars().all(|c| c == first))
+    .then_some(first)
+}
+
+/// `=====  ======` borders of a simple table.
+fn is_table_border(line: &str) -> bool {
+    let trimmed = line.trim_end();
+    trimmed.starts_with('=')
+        && trimmed.chars().all(|c| c == '=' || c == ' ')
+        && trimmed.split_whitespace().count() >= 2
+}
+
+/// Marker and content column of a bullet or enumerated list item.
+fn list_marker(line: &str) -> Option<(String, usize)> {
+    let start = indent(line);
+    let rest = &line[start..];
+    for bullet in ["* ", "- ", "+ ", "• "] {
+        if let Some(after) = rest.strip_prefix(bullet) {
+            let spaces = after.len() - after.trim_start().len();
+            return Some(("-".to_string(), start + bullet.len() + spaces));
+        }
+    }
+
+    let (number, marker_len) = if let Some(after) = rest.strip_prefix("#.") {
+        (after.starts_with(' ').then_some("1")?, 2)
+    } else {
+        let open = usize::from(rest.starts_with('('));
+        let digits = rest[open..]
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .count();
+        if digits == 0 {
+            return None;
+        }
+        let after = &rest[open + digits..];
+        let close = if open == 1 {
+            after.starts_with(") ").then_some(1)?
+        } else if after.starts_with(". ") || after.starts_with(") ") {
+            1
+        } else {
+            return None;
+        };
+        (&rest[open..open + digits], open + digits + close)
+    };
+    let after = &rest[marker_len..];
+    let spaces = after.len() - after.trim_start().len();
+    Some((format!("{}.", number), start + marker_len + spaces))
+}
+
+struct Converter<'a> {
+    lines: Vec<&'a str>,
+    out: Vec<String>,
+    targets: HashMap<String, String>,
+    /// Section adornment styles in order of first use; a title's level is
+    /// its style's position.
+    styles: Vec<(char, bool)>,
+    /// Content column of the list item being continued.
+    list: Option<usize>,
+}
+
+impl<'a> Converter<'a> {
+    fn line(&self, i: usize) -> &'a str {
+        self.lines.get(i).copied().unwrap_or("")
+    }
+
+    fn heading(&mut self, style: (char, bool), title: &str) -> String {
+        let level = match self.styles.iter().position(|s| *s == style) {
+            Some(index) => index + 1,
+            None => {
+                self.styles.push(style);
+                self.styles.len()
+            }
+        };
+        format!("{} {}", "#".repeat(level.min(6)), self.inline(title.trim()))
+    }
+
+    /// Converts the block starting at line `i` and returns the index of the
+    /// line after it.
+    fn step(&mut self, i: usize) -> usize {
+        let line = self.line(i);
+        if is_blank(line) {
+            self.out.push(String::new());
+            return i + 1;
+        }
+        let at = indent(line);
+        let trimmed = line.trim();
+
+        if self.list.is_some_and(|column| at < column) && list_marker(line).is_none() {
+            self.list = None;
+        }
+
+        // Title with an overline.
+        if let Some(c) = adornment(line) {
+            let title = self.line(i + 1);
+            if !is_blank(title)
+                && adornment(self.line(i + 2)) == Some(c)
+                && adornment(title).is_none()
+            {
+                let heading = self.heading((c, true), title);
+                self.out.extend([String::new(), heading, String::new()]);
+                return i + 3;
+            }
+            if trimmed.len() >= 4 && (i == 0 || is_blank(self.line(i - 1))) {
+                self.out.push("***".to_string());
+                return i + 1;
+            }
+        }
+
+        // Title with an underline only.
+        if at == 0 && adornment(line).is_none() && !is_table_border(self.line(i + 1)) {
+            if let Some(c) = adornment(self.line(i + 1)) {
+                let heading = self.heading((c, false), trimmed);
+                self.out.extend([heading, String::new()]);
+                return i + 2;
+            }
+        }
+
+        if at == 0 && is_table_border(line) {
+            return self.simple_table(i);
+        }
+        if trimmed.starts_with("+-") || trimmed.starts_with("+=") {
+            return self.grid_table(i);
+        }
+        if trimmed == ".." || trimmed.starts_with(".. ") {
+            return self.explicit_markup(i);
+        }
+
+        let converted = if let Some((marker, column)) = list_marker(line) {
+            self.list = Some(column);
+            format!(
+                "{}{} {}",
+                " ".repeat(at),
+                marker,
+                self.inline(&line[column..])
+            )
+        } else if self.list.is_some() {
+            format!("{}{}", " ".repeat(at), self.inline(trimmed))
+        } else if let Some(field) = field(trimmed) {
+            let (name, value) = field;
+            format!("- **{}:** {}", name, self.inline(value))
+        } else if at > 0 {
+            // Indented text outside a list is a block quote.
+            format!("> {}", self.inline(trimmed))
+        } else {
+            self.inline(trimmed)
+        };
+
+        // `Paragraph::` introduces a literal block.
+        if let Some(text) = converted.strip_suffix("::") {
+            let text = if text.trim().is_empty() || text.ends_with(' ') {
+                text.trim_end().to_string()
+            } else {
+                format!("{}:", text)
+            };
+            self.out.push(text);
+            let prefix = self.list.map_or(0, |column| at.max(column));
+            return self.code_block(i + 1, at, prefix, "text");
+        }
+        self.out.push(converted);
+        i + 1
+    }
+
+    /// Lines from `start` that are blank or indented past `base`, as a
+    /// fenced code block indented by `prefix`.
+    fn code_block(&mut self, start: usize, base: usize, prefix: usize, language: &str) -> usize {
+        let (first, last, end) = self.body(start, base);
+        if first == last {
+            self.blank_lines(end - start);
+            return end;
+        }
+
+        let dedent = (first..last)
+            .map(|j| self.line(j))
+            .filter(|line| !is_blank(line))
+            .map(indent)
+            .min()
+            .unwrap_or(0);
+        let body: Vec<&str> = (first..last)
+            .map(|j| self.line(j))
+            .map(|line| if is_blank(line) { "" } else { &line[dedent..] })
+            .collect();
+        let pad = " ".repeat(prefix);
+        let fenced: Vec<String> = fence(&body.join("\n"), language)
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    String::new()
+                } else {
+                    format!("{}{}", pad, line)
+                }
+            })
+            .collect();
+
+        // The fences take the place of the blank lines around the body, so
+        // the lines after it keep their numbers.
+        self.blank_lines((first - start).saturating_sub(1));
+        self.out.push(fenced.join("\n"));
+        self.blank_lines((end - last).saturating_sub(1));
+        end
+    }
+
+    /// The indented body of a block starting at `start`: index of its first
+    /// and one past its last non-blank line, and one past the trailing
+    /// blank lines that belong to it.
+    fn body(&self, start: usize, base: usize) -> (usize, usize, usize) {
+        let mut end = start;
+        while end < self.lines.len() && (is_blank(self.line(end)) || indent(self.line(end)) > base)
+        {
+            end += 1;
+        }
+        let mut last = end;
+        while last > start && is_blank(self.line(last - 1)) {
+            last -= 1;
+        }
+        let mut first = start;
+        while first < last && is_blank(self.line(first)) {
+            first += 1;
+        }
+        (first, last, end)
+    }
+
+    fn blank_lines(&mut self, count: usize) {
+        self.out.extend(std::iter::repeat_n(String::new(), count));
+    }
+
+    /// `.. directive:: arguments`, footnotes, link targets and comments.
+    fn explicit_markup(&mut self, i: usize) -> usize {
+        let line = self.line(i);
+        let at = indent(line);
+        let text = line.trim().trim_start_matches("..").trim_start();
+
+        if let Some(footnote) = text.strip_prefix('[') {
+            if let Some((label, body)) = footnote.split_once("] ") {
+                let label = label.trim_start_matches('#');
+                self.out
+                    .push(format!("[^{}]: {}", label, self.inline(body)));
+                let (_, last, end) = self.body(i + 1, at);
+                for j in i + 1..end {
+                    let line = self.line(j);
+                    if j < last && !is_blank(line) {
+                        self.out.push(format!("    {}", self.inline(line.trim())));
+                    } else {
+                        self.out.push(String::new());
+                    }
+                }
+                return end;
+            }
+        }
+
+        let directive = text
+            .split_once("::")
+            .filter(|(name, _)| !name.is_empty() && !name.contains(char::is_whitespace));
+        let Some((name, arguments)) = directive else {
+            // Comments, link targets and substitution definitions.
+            return self.skip(i, at);
+        };
+        let name = name.to_lowercase();
+        let arguments = arguments.trim();
+
+        if CODE_DIRECTIVES.contains(&name.as_str()) || name == "math" {
+            let language = if name == "math" {
+                "math"
+            } else {
+                arguments.split_whitespace().next().unwrap_or("text")
+            };
+            let language = language.to_string();
+            self.out.push(String::new());
+            let body = self.skip_options(i + 1, at);
+            let prefix = self.list.map_or(0, |column| column.min(at));
+            return self.code_block(body, at, prefix, &language);
+        }
+
+        if let Some((_, alert)) = ADMONITIONS.iter().find(|(directive, _)| *directive == name) {
+            return self.admonition(i, at, alert, &name, arguments);
+        }
+
+        if name == "image" || name == "figure" {
+            return self.image(i, at, &name, arguments);
+        }
+
+        self.skip(i, at)
+    }
+
+    /// Blanks out a line and its indented body.
+    fn skip(&mut self, i: usize, base: usize) -> usize {
+        let (_, _, end) = self.body(i + 1, base);
+        self.blank_lines(end - i);
+        end
+    }
+
+    /// Blanks out directive options (`:linenos:`) and returns the line
+    /// after them.
+    fn skip_options(&mut self, start: usize, base: usize) -> usize {
+        let mut j = start;
+        while j < self.lines.len() {
+            let line = self.line(j);
+            if is_blank(line) || indent(line) <= base || !line.trim().starts_with(':') {
+                break;
+            }
+            self.out.push(String::new());
+            j += 1;
+        }
+        j
+    }
+
+    fn options(&self, start: usize, base: usize) -> HashMap<String, String> {
+        let mut options = HashMap::new();
+        for j in start.. {
+            let line = self.line(j);
+            if j >= self.lines.len() || is_blank(line) || indent(line) <= base {
+                break;
+            }
+            let Some((name, value)) = line
+                .trim()
+                .strip_prefix(':')
+                .and_then(|o| o.split_once(':'))
+            else {
+                break;
+            };
+            options.insert(name.to_string(), value.trim().to_string());
+        }
+        options
+    }
+
+    fn admonition(
+        &mut self,
+        i: usize,
+        at: usize,
+        alert: &str,
+        name: &str,
+        arguments: &str,
+    ) -> usize {
+        let pad = " ".repeat(self.list.map_or(0, |column| column.min(at)));
+        let mut opening = format!("{}> [!{}]", pad, alert);
+        if name == "admonition" {
+            // The generic admonition's argument is its title.
+            opening.push(' ');
+            opening.push_str(&self.inline(arguments));
+        } else if name == "seealso" {
+            opening.push_str(" See also");
+        }
+        if name != "admonition" && !arguments.is_empty() {
+            opening.push_str(&format!("\n{}> {}", pad, self.inline(arguments)));
+        }
+        self.out.push(opening);
+
+        let body = self.skip_options(i + 1, at);
+        let (first, last, end) = self.body(body, at);
+        let dedent = (first..last)
+            .map(|j| self.line(j))
+            .filter(|line| !is_blank(line))
+            .map(indent)
+            .min()
+            .unwrap_or(0);
+        for j in body..end {
+            let line = self.line(j);
+            if j >= last {
+                self.out.push(String::new());
+            } else if is_blank(line) {
+                self.out.push(format!("{}>", pad));
+            } else {
+                let inner = &line[dedent..];
+                let start = inner.len() - inner.trim_start().len();
+                self.out.push(format!(
+                    "{}> {}{}",
+                    pad,
+                    &inner[..start],
+                    self.inline(inner.trim_start())
+                ));
+            }
+        }
+        end
+    }
+
+    fn image(&mut self, i: usize, at: usize, name: &str, arguments: &str) -> usize {
+        let options = self.options(i + 1, at);
+        let alt = options.get("alt").map(String::as_str).unwrap_or("");
+        let image = format!("![{}]({})", alt, arguments.replace(' ', "%20"));
+        self.out.push(match options.get("target") {
+            Some(target) => format!("[{}]({})", image, target),
+            None => image,
+        });
+
+        let body = self.skip_options(i + 1, at);
+        let (first, last, end) = self.body(body, at);
+        for j in body..end {
+            let line = self.line(j);
+            // A figure's first paragraph is its caption.
+            if name == "figure" && j >= first && j < last && !is_blank(line) {
+                self.out.push(format!("*{}*", self.inline(line.trim())));
+            } else {
+                self.out.push(String::new());
+            }
+        }
+        end
+    }
+
+    /// Splits a row of a simple table at the column starts.
+    fn cells(&self, line: &str, columns: &[usize]) -> Vec<String> {
+        let chars: Vec<char> = line.chars().collect();
+        columns
+            .iter()
+            .enumerate()
+            .map(|(k, &start)| {
+                let end = columns
+                    .get(k + 1)
+                    .copied()
+                    .unwrap_or(chars.len())
+                    .min(chars.len());
+                let cell: String = chars
+                    .get(start.min(end)..end)
+                    .unwrap_or(&[])
+                    .iter()
+                    .collect();
+                self.inline(cell.trim()).replace('|', "\\|")
+            })
+            .collect()
+    }
+
+    /// Simple tables (`===` borders) as pipe tables.
+    fn simple_table(&mut self, i: usize) -> usize {
+        let border = self.line(i);
+        let mut columns = Vec::new();
+        let mut previous = ' ';
+        for (k, c) in border.chars().enumerate() {
+            if c == '=' && previous == ' ' {
+                columns.push(k);
+            }
+            previous = c;
+        }
+
+        // Borders after the first: one more with a header row, one without.
+        let mut borders = Vec::new();
+        let mut j = i + 1;
+        while j < self.lines.len() {
+            if is_table_border(self.line(j)) {
+                borders.push(j);
+                if j + 1 >= self.lines.len() || is_blank(self.line(j + 1)) {
+                    break;
+                }
+            }
+            j += 1;
+        }
+        let Some(&end) = borders.last() else {
+            self.out.push(self.inline(border.trim()));
+            return i + 1;
+        };
+        let header_end = (borders.len() > 1).then(|| borders[0]);
+
+        let mut header = Vec::new();
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        for j in i + 1..end {
+            let line = self.line(j);
+            if is_blank(line) || is_table_border(line) {
+                continue;
+            }
+            let cells = self.cells(line, &columns);
+            let group = if header_end.is_some_and(|h| j < h) {
+                &mut header
+            } else {
+                &mut rows
+            };
+            // A row with an empty first cell continues the one above.
+            match group.last_mut() {
+                Some(previous) if cells[0].is_empty() => join_cells(previous, cells),
+                _ => group.push(cells),
+            }
+        }
+        if header.is_empty() && !rows.is_empty() {
+            header.push(rows.remove(0));
+        }
+        let header = header
+            .into_iter()
+            .reduce(|mut first, more| {
+                join_cells(&mut first, more);
+                first
+            })
+            .unwrap_or_else(|| vec![String::new(); columns.len()]);
+
+        // Pipe tables can't have blank lines between rows, so the rows are
+        // written together and the lines left over stay blank.
+        let row = |cells: &[String]| format!("| {} |", cells.join(" | "));
+        let mut table = vec![
+            String::new(),
+            row(&header),
+            format!("|{}", " --- |".repeat(columns.len())),
+        ];
+        table.extend(rows.iter().map(|cells| row(cells)));
+        table.resize(table.len().max(end - i + 1), String::new());
+        self.out.extend(table);
+        end + 1
+    }
+
+    /// Grid tables are shown as drawn.
+    fn grid_table(&mut self, i: usize) -> usize {
+        let mut end = i;
+        while end < self.lines.len() {
+            let trimmed = self.line(end).trim();
+            if !(trimmed.starts_with('+') || trimmed.starts_with('|')) {
+                break;
+            }
+            end += 1;
+        }
+        let table: Vec<&str> = (i..end).map(|j| self.line(j).trim()).collect();
+        self.out.push(fence(&table.join("\n"), "text"));
+        end
+    }
+
+    /// Converts rst inline markup: ``literal``, `interpreted`, roles,
+    /// hyperlink references and footnote references. `*emphasis*` and
+    /// `**strong**` read the same in markdown.
+    fn inline(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::with_capacity(text.len());
+        let mut i = 0;
+
+        let find = |from: usize, pattern: &str| -> Option<usize> {
+            let rest: String = chars[from..].iter().collect();
+            rest.find(pattern)
+                .map(|at| from + rest[..at].chars().count())
+        };
+
+        while i < chars.len() {
+            let c = chars[i];
+
+            if c == '\\' && i + 1 < chars.len() {
+                out.push(c);
+                out.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+
+            if c == '`' && chars.get(i + 1) == Some(&'`') {
+                if let Some(close) = find(i + 2, "``") {
+                    let inner: String = chars[i + 2..close].iter().collect();
+                    out.push_str(&code_span(&inner));
+                    i = close + 2;
+                    continue;
+                }
+            }
+
+            // :role:`text`
+            if c == ':' && (i == 0 || !chars[i - 1].is_alphanumeric()) {
+                let name_len = chars[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_alphanumeric() || "-_.+:".contains(**c))
+                    .count();
+                let tick = i + 1 + name_len;
+                if name_len > 1 && chars[tick - 1] == ':' && chars.get(tick) == Some(&'`') {
+                    if let Some(close) = find(tick + 1, "`") {
+                        let role: String = chars[i + 1..tick - 1].iter().collect();
+                        let inner: String = chars[tick + 1..close].iter().collect();
+                        out.push_str(&role_text(&role, &inner));
+                        i = close + 1;
+                        continue;
+                    }
+                }
+            }
+
+            if c == '`' {
+                if let Some(close) = find(i + 1, "`") {
+                    let inner: String = chars[i + 1..close].iter().collect();
+                    let underscores = chars[close + 1..].iter().take_while(|&&c| c == '_').count();
+                    out.push_str(&if underscores > 0 {
+                        self.reference(&inner)
+                    } else {
+                        format!("*{}*", inner)
+                    });
+                    i = close + 1 + underscores.min(2);
+                    continue;
+                }
+            }
+
+            // Footnote references: [1]_, [#note]_
+            if c == '[' {
+                if let Some(close) = find(i + 1, "]_") {
+                    let label: String = chars[i + 1..close].iter().collect();
+                    if !label.is_empty() && !label.contains(char::is_whitespace) {
+                        out.push_str(&format!("[^{}]", label.trim_start_matches('#')));
+                        i = close + 2;
+                        continue;
+                    }
+                }
+            }
+
+            // word_ references
+            if c == '_' && i > 0 && chars[i - 1].is_alphanumeric() {
+                let ends = chars.get(i + 1).is_none_or(|next| !next.is_alphanumeric());
+                if ends {
+                    let start = out
+                        .char_indices()
+                        .rev()
+                        .take_while(|(_, c)| c.is_alphanumeric() || *c == '-')
+                        .last()
+                        .map(|(at, _)| at);
+                    if let Some(start) = start {
+                        let word = out.split_off(start);
+                        out.push_str(&self.reference(&word));
+                        i += 1 + usize::from(chars.get(i + 1) == Some(&'_'));
+                        continue;
+                    }
+                }
+            }
+
+            out.push(c);
+            i += 1;
+        }
+        out
+    }
+
+    /// `text <url>`, or a named reference resolved through the document's
+    /// targets, falling back to a section of the same name.
+    fn reference(&self, text: &str) -> String {
+        if let Some((label, url)) = text.strip_suffix('>').and_then(|t| t.rsplit_once('<')) {
+            let label = label.trim();
+            let label = if label.is_empty() { url } else { label };
+            return format!("[{}]({})", label, url.replace(' ', "%20"));
+        }
+        match self.targets.get(&text.to_lowercase()) {
+            Some(url) => format!("[{}]({})", text, url),
+            None => format!(
+                "[{}](#{})",
+                text,
+                Anchorizer::new().anchorize(text.to_string())
+            ),
+        }
+    }
+}
+
+/// `:Author: Jane` docinfo and field list lines.
+fn field(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix(':')?;
+    let (name, value) = rest
+        .split_once(": ")
+        .or_else(|| rest.strip_suffix(':').map(|n| (n, "")))?;
+    (!name.is_empty() && !name.contains('`')).then_some((name, value.trim()))
+}
+
+fn join_cells(cells: &mut [String], more: Vec<String>) {
+    for (cell, more) in cells.iter_mut().zip(more) {
+        if !more.is_empty() {
+            if !cell.is_empty() {
+                cell.push(' ');
+            }
+            cell.push_str(&more);
+        }
+    }
+}
+
+fn code_span(text: &str) -> String {
+    if text.contains('`') {
+        format!("`` {} ``", text)
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+/// Text of an interpreted role. Cross-references (`:ref:`, `:doc:`) show
+/// their title; code-like roles become code.
+fn role_text(role: &str, text: &str) -> String {
+    let title = text
+        .strip_suffix('>')
+        .and_then(|t| t.rsplit_once('<'))
+        .map_or(text, |(title, _)| title.trim());
+    match role {
+        "emphasis" | "title-reference" | "title" | "t" => format!("*{}*", title),
+        "strong" => format!("**{}**", title),
+        "code" | "literal" | "file" | "command" | "samp" | "kbd" | "envvar" | "program"
+        | "option" | "func" | "meth" | "class" | "attr" | "mod" | "data" | "obj" | "exc"
+        | "py:func" | "py:meth" | "py:class" | "py:attr" | "py:mod" | "py:obj" | "py:exc" => {
+            code_span(title)
+        }
+        "sub" | "subscript" => format!("<sub>{}</sub>", title),
+        "sup" | "superscript" => format!("<sup>{}</sup>", title),
+        _ => title.to_string(),
+    }
+}
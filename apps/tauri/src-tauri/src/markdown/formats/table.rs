@@ -0,0 +1,233 @@
+//! CSV and TSV files, shown as a sortable table.
+//!
+//! Only the first page is rendered with the document. The table view
+//! fetches other pages, sorted by any column, through [`page`], so large
+//! files never end up in the DOM all at once.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use super::{escape, Source};
+
+/// Rows per page, in the document and in [`page`].
+pub const PAGE_SIZE: usize = 200;
+
+/// How many data rows header detection looks at.
+const SAMPLE_ROWS: usize = 50;
+
+pub struct Table {
+    /// Column names, when the first row looks like a header.
+    pub header: Option<Vec<String>>,
+    pub rows: Vec<Vec<String>>,
+    pub columns: usize,
+}
+
+/// Sort order requested by the table view.
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct Sort {
+    pub column: usize,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// A page of rows for the table view.
+#[derive(Debug, Serialize, Clone)]
+pub struct Page {
+    pub rows: Vec<Vec<String>>,
+    /// Index of the first row on the page.
+    pub start: usize,
+    pub total: usize,
+}
+
+/// Reads delimited text. TSV is read without quoting, as most tools write
+/// it; rows may have different lengths.
+pub fn parse(content: &str, delimiter: u8) -> Result<Table, String> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .quoting(delimiter != b'\t')
+        .delimiter(delimiter)
+        .from_reader(content.as_bytes());
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Failed to parse table: {}", e))?;
+        rows.push(record.iter().map(str::to_string).collect::<Vec<_>>());
+    }
+
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let header = if has_header(&rows) {
+        Some(rows.remove(0))
+    } else {
+        None
+    };
+    Ok(Table {
+        header,
+        rows,
+        columns,
+    })
+}
+
+pub fn convert(content: &str, delimiter: u8) -> Source {
+    let mut source = Source::default();
+    match parse(content, delimiter) {
+        Ok(table) => source.push_embed("data-table", render(&table)),
+        Err(e) => {
+            source.push_block(&format!("> [!CAUTION]\n> {}", e));
+            source.push_block(&super::fence(content, "text"));
+        }
+    }
+    source
+}
+
+/// Rows `page * PAGE_SIZE..` of the table in `content`, sorted first when
+/// `sort` is given.
+pub fn page(content: &str, delimiter: u8, page: usize, sort: Option<Sort>) -> Result<Page, String> {
+    let mut table = parse(content, delimiter)?;
+    if let Some(sort) = sort {
+        sort_rows(&mut table.rows, sort);
+    }
+
+    let total = table.rows.len();
+    let start = (page * PAGE_SIZE).min(total);
+    let rows = table.rows.drain(start..).take(PAGE_SIZE).collect();
+    Ok(Page { rows, start, total })
+}
+
+/// Sorts numbers numerically and text case-insensitively. Empty cells stay
+/// at the bottom in either direction.
+fn sort_rows(rows: &mut [Vec<String>], sort: Sort) {
+    rows.sort_by(|a, b| {
+        let a = a.get(sort.column).map_or("", |c| c.trim());
+        let b = b.get(sort.column).map_or("", |c| c.trim());
+        match (a.is_empty(), b.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) if sort.descending => compare(b, a),
+            (false, false) => compare(a, b),
+        }
+    });
+}
+
+fn compare(a: &str, b: &str) -> Ordering {
+    match (number(a), number(b)) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+fn number(cell: &str) -> Option<f64> {
+    let cell = cell.trim();
+    cell.strip_suffix('%')
+        .unwrap_or(cell)
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite())
+}
+
+/// Whether every non-empty cell of a column is a number.
+fn is_numeric(rows: &[Vec<String>], column: usize) -> bool {
+    let mut cells = rows
+        .iter()
+        .filter_map(|row| row.get(column))
+        .filter(|c| !c.trim().is_empty())
+        .peekable();
+    cells.peek().is_some() && cells.all(|c| number(c).is_some())
+}
+
+/// The first row is a header when its cells are distinct, non-empty text:
+/// no number sits at the top of a numeric column, and no cell repeats
+/// further down its column.
+fn has_header(rows: &[Vec<String>]) -> bool {
+    let Some((first, data)) = rows.split_first() else {
+        return false;
+    };
+    let sample = &data[..data.len().min(SAMPLE_ROWS)];
+
+    for (column, name) in first.iter().enumerate() {
+        let name = name.trim();
+        let duplicate = first[..column].iter().any(|other| other.trim() == name);
+        if name.is_empty() || duplicate || number(name).is_some() {
+            return false;
+        }
+        if sample
+            .iter()
+            .any(|row| row.get(column).is_some_and(|c| c.trim() == name))
+        {
+            return false;
+        }
+    }
+    true
+}
+
+/// Spreadsheet-style names for columns without a header: A…Z, AA, AB…
+fn column_letter(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+fn render(table: &Table) -> String {
+    let numeric: Vec<bool> = (0..table.columns)
+        .map(|column| is_numeric(&table.rows, column))
+        .collect();
+    let cell_class = |column: usize| {
+        if numeric[column] {
+            " class=\"numeric\""
+        } else {
+            ""
+        }
+    };
+
+    let mut html = format!(
+        "<table data-total=\"{}\" data-page-size=\"{}\" data-header=\"{}\"><thead><tr>",
+        table.rows.len(),
+        PAGE_SIZE,
+        table.header.is_some()
+    );
+    for column in 0..table.columns {
+        let name = match &table.header {
+            Some(header) => header.get(column).cloned().unwrap_or_default(),
+            None => column_letter(column),
+        };
+        html.push_str(&format!(
+            "<th data-column=\"{}\"{}>{}</th>",
+            column,
+            cell_class(column),
+            escape(&name)
+        ));
+    }
+    html.push_str("</tr></thead><tbody>");
+
+    for row in table.rows.iter().take(PAGE_SIZE) {
+        html.push_str("<tr>");
+        for column in 0..table.columns {
+            let cell = row.get(column).map_or("", String::as_str);
+            html.push_str(&format!("<td{}>{}</td>", cell_class(column), escape(cell)));
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</tbody></table>");
+
+    let shown = table.rows.len().min(PAGE_SIZE);
+    html.push_str(&format!(
+        "<div class=\"data-table-status\">{}</div>",
+        if shown == table.rows.len() {
+            format!("{} row{}", shown, if shown == 1 { "" } else { "s" })
+        } else {
+            format!("Rows 1–{} of {}", shown, table.rows.len())
+        }
+    ));
+    html
+}
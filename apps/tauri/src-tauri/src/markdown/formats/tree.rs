@@ -0,0 +1,141 @@
+//! JSON, YAML and TOML files, shown as a collapsible tree.
+//!
+//! Values are rendered as nested `<details>` elements, so collapsing works
+//! without any script, in exported HTML too. A file that doesn't parse is
+//! shown as highlighted source under the parser error instead.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{escape, fence, Source};
+use crate::markdown::front_matter;
+
+/// Levels open when the document is shown.
+const OPEN_DEPTH: usize = 2;
+
+/// Entries rendered per object or array; the rest are counted.
+const MAX_ENTRIES: usize = 500;
+
+pub fn json(content: &str) -> Result<Vec<Value>, String> {
+    serde_json::from_str(content)
+        .map(|value| vec![value])
+        .map_err(|e| format!("Failed to parse JSON: {}", e))
+}
+
+/// Every document of a multi-document stream becomes its own tree.
+pub fn yaml(content: &str) -> Result<Vec<Value>, String> {
+    serde_yaml::Deserializer::from_str(content)
+        .map(Value::deserialize)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to parse YAML: {}", e))
+}
+
+pub fn toml(content: &str) -> Result<Vec<Value>, String> {
+    toml::from_str::<toml::Value>(content)
+        .map(|value| vec![front_matter::toml_to_json(value)])
+        .map_err(|e| format!("Failed to parse TOML: {}", e))
+}
+
+pub fn convert(
+    content: &str,
+    language: &str,
+    parse: fn(&str) -> Result<Vec<Value>, String>,
+) -> Source {
+    let mut source = Source::default();
+    match parse(content) {
+        Ok(documents) => {
+            for document in &documents {
+                let mut html = String::new();
+                node(&mut html, Key::Root, document, 0);
+                source.push_embed("data-tree", html);
+            }
+        }
+        Err(e) => {
+            source.push_block(&format!("> [!CAUTION]\n> {}", e.replace('\n', " ")));
+            source.push_block(&fence(content, language));
+        }
+    }
+    source
+}
+
+enum Key<'a> {
+    Root,
+    Name(&'a str),
+    Index(usize),
+}
+
+fn key_html(key: Key) -> String {
+    match key {
+        Key::Root => String::new(),
+        Key::Name(name) => format!(
+            "<span class=\"dt-key\">{}</span><span class=\"dt-punct\">: </span>",
+            escape(name)
+        ),
+        Key::Index(index) => format!(
+            "<span class=\"dt-index\">{}</span><span class=\"dt-punct\">: </span>",
+            index
+        ),
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+}
+
+fn node(html: &mut String, key: Key, value: &Value, depth: usize) {
+    let (summary, entries): (String, Vec<(Key, &Value)>) = match value {
+        Value::Object(map) if !map.is_empty() => (
+            plural(map.len(), "key"),
+            map.iter()
+                .map(|(name, value)| (Key::Name(name), value))
+                .collect(),
+        ),
+        Value::Array(items) if !items.is_empty() => (
+            plural(items.len(), "item"),
+            items
+                .iter()
+                .enumerate()
+                .map(|(index, value)| (Key::Index(index), value))
+                .collect(),
+        ),
+        _ => {
+            html.push_str(&format!(
+                "<div class=\"dt-leaf\">{}{}</div>",
+                key_html(key),
+                scalar(value)
+            ));
+            return;
+        }
+    };
+
+    let brackets = if value.is_object() { "{…}" } else { "[…]" };
+    html.push_str(&format!(
+        "<details{}><summary>{}<span class=\"dt-punct\">{}</span> <span class=\"dt-summary\">{}</span></summary><div class=\"dt-children\">",
+        if depth < OPEN_DEPTH { " open" } else { "" },
+        key_html(key),
+        brackets,
+        summary
+    ));
+    let hidden = entries.len().saturating_sub(MAX_ENTRIES);
+    for (key, value) in entries.into_iter().take(MAX_ENTRIES) {
+        node(html, key, value, depth + 1);
+    }
+    if hidden > 0 {
+        html.push_str(&format!("<div class=\"dt-more\">… {} more</div>", hidden));
+    }
+    html.push_str("</div></details>");
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "<span class=\"dt-null\">null</span>".to_string(),
+        Value::Bool(b) => format!("<span class=\"dt-bool\">{}</span>", b),
+        Value::Number(n) => format!("<span class=\"dt-number\">{}</span>", n),
+        Value::String(s) => format!(
+            "<span class=\"dt-string\">\"{}\"</span>",
+            escape(&s.replace('\\', "\\\\").replace('"', "\\\""))
+        ),
+        Value::Object(_) => "<span class=\"dt-punct\">{}</span>".to_string(),
+        Value::Array(_) => "<span class=\"dt-punct\">[]</span>".to_string(),
+    }
+}
@@ -0,0 +1,90 @@
+use serde::Serialize;
+
+/// Front matter block found at the very top of a document, already parsed
+/// into JSON so the frontend can display and filter on it.
+#[derive(Debug, Serialize, Clone)]
+pub struct FrontMatter {
+    /// `"yaml"` for `---` delimited blocks, `"toml"` for `+++`.
+    pub format: String,
+    /// The block contents without the delimiter lines.
+    pub raw: String,
+    /// Parsed contents; `null` when the block is empty or failed to parse.
+    pub data: serde_json::Value,
+    /// Parser error message, so a typo shows up in the panel instead of
+    /// silently hiding the metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Returns the front matter delimiter the document opens with, if any.
+/// Only `---` (YAML) and `+++` (TOML) on the very first line are accepted.
+pub fn detect_delimiter(content: &str) -> Option<&'static str> {
+    let first_line = content.lines().next()?.trim_end();
+    match first_line {
+        "---" => Some("---"),
+        "+++" => Some("+++"),
+        _ => None,
+    }
+}
+
+/// Parses the literal of a comrak `FrontMatter` node, which still includes
+/// both delimiter lines and any trailing blank lines.
+pub fn parse(literal: &str, delimiter: &str) -> FrontMatter {
+    let raw = strip_delimiters(literal, delimiter);
+
+    let (format, parsed) = if delimiter == "+++" {
+        let parsed = toml::from_str::<toml::Value>(&raw)
+            .map(toml_to_json)
+            .map_err(|e| e.to_string());
+        ("toml", parsed)
+    } else {
+        let parsed = serde_yaml::from_str::<serde_json::Value>(&raw).map_err(|e| e.to_string());
+        ("yaml", parsed)
+    };
+
+    let (data, error) = match parsed {
+        Ok(value) => (value, None),
+        Err(e) => (serde_json::Value::Null, Some(e)),
+    };
+
+    FrontMatter {
+        format: format.to_string(),
+        raw,
+        data,
+        error,
+    }
+}
+
+/// TOML datetimes serialize as a private wrapper object through serde, so
+/// values are converted by hand to keep dates as plain strings.
+pub(crate) fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(s) => serde_json::Value::String(s),
+        toml::Value::Integer(i) => serde_json::Value::from(i),
+        toml::Value::Float(f) => serde_json::Value::from(f),
+        toml::Value::Boolean(b) => serde_json::Value::Bool(b),
+        toml::Value::Datetime(d) => serde_json::Value::String(d.to_string()),
+        toml::Value::Array(items) => items.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(table) => table
+            .into_iter()
+            .map(|(k, v)| (k, toml_to_json(v)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+    }
+}
+
+fn strip_delimiters(literal: &str, delimiter: &str) -> String {
+    let mut lines: Vec<&str> = literal.lines().collect();
+
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+    if lines.last().is_some_and(|l| l.trim_end() == delimiter) {
+        lines.pop();
+    }
+    if lines.first().is_some_and(|l| l.trim_end() == delimiter) {
+        lines.remove(0);
+    }
+
+    lines.join("\n")
+}
@@ -0,0 +1,67 @@
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::Anchorizer;
+use serde::Serialize;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Heading {
+    pub level: u8,
+    /// Heading text with inline markup removed (`**bold**` becomes `bold`).
+    pub text: String,
+    /// Position among all headings in the document, matching the order of
+    /// `h1`–`h6` elements in the rendered HTML.
+    pub index: usize,
+    /// 1-based markdown line the heading starts on.
+    pub line: usize,
+    /// GitHub-compatible anchor, deduplicated with `-1`, `-2`… suffixes the
+    /// same way comrak's `header_ids` extension does.
+    pub slug: String,
+}
+
+pub fn extract<'a>(root: &'a AstNode<'a>) -> Vec<Heading> {
+    let mut anchorizer = Anchorizer::new();
+
+    root.descendants()
+        .filter_map(|node| {
+            let ast = node.data.borrow();
+            match ast.value {
+                NodeValue::Heading(ref heading) => Some((node, heading.level, ast.sourcepos.start.line)),
+                _ => None,
+            }
+        })
+        .enumerate()
+        .map(|(index, (node, level, line))| {
+            let text = plain_text(node);
+            let slug = anchorizer.anchorize(text.clone());
+            Heading {
+                level,
+                text,
+                index,
+                line,
+                slug,
+            }
+        })
+        .collect()
+}
+
+/// Concatenates the visible text of an inline subtree, the way it reads in
+/// the rendered document.
+pub fn plain_text<'a>(node: &'a AstNode<'a>) -> String {
+    let mut text = String::new();
+    collect_text(node, &mut text);
+    text.trim().to_string()
+}
+
+fn collect_text<'a>(node: &'a AstNode<'a>, text: &mut String) {
+    match &node.data.borrow().value {
+        NodeValue::Text(literal) => text.push_str(literal),
+        NodeValue::Code(code) => text.push_str(&code.literal),
+        NodeValue::Math(math) => text.push_str(&math.literal),
+        NodeValue::SoftBreak | NodeValue::LineBreak => text.push(' '),
+        NodeValue::HtmlInline(_) | NodeValue::FootnoteReference(_) => {}
+        _ => {
+            for child in node.children() {
+                collect_text(child, text);
+            }
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use comrak::adapters::SyntaxHighlighterAdapter;
+use comrak::html;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, Theme, ThemeSet};
+use syntect::html::{append_highlighted_html_for_styled_line, IncludeBackground};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+const LIGHT_THEME: &str = "InspiredGitHub";
+const DARK_THEME: &str = "base16-ocean.dark";
+
+/// Color scheme used for highlighted code blocks, chosen by the frontend
+/// from the resolved app theme on every render.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CodeTheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// Syntax and theme sets take a noticeable time to load, so they are built
+/// once and shared by every render.
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// Extensions and file names the bundled grammars are registered for.
+pub fn grammar_extensions() -> impl Iterator<Item = &'static str> {
+    syntax_set()
+        .syntaxes()
+        .iter()
+        .flat_map(|syntax| syntax.file_extensions.iter().map(String::as_str))
+}
+
+fn theme_set() -> &'static ThemeSet {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+/// comrak code fence adapter backed by syntect.
+///
+/// Unlike comrak's bundled `SyntectAdapter`, blocks whose language syntect
+/// doesn't know are written as escaped plain text instead of being run
+/// through the plain-text grammar, and the `<pre>` keeps the app's own
+/// background so highlighted blocks match the rest of the stylesheet.
+pub struct Highlighter {
+    theme: &'static Theme,
+    line_numbers: bool,
+}
+
+impl Highlighter {
+    pub fn new(theme: CodeTheme) -> Self {
+        let name = match theme {
+            CodeTheme::Light => LIGHT_THEME,
+            CodeTheme::Dark => DARK_THEME,
+        };
+        Self {
+            theme: &theme_set().themes[name],
+            line_numbers: false,
+        }
+    }
+
+    /// Wraps every line in a numbered `<span class="line" id="L<n>">`, for
+    /// source files shown as a single block.
+    pub fn with_line_numbers(mut self, line_numbers: bool) -> Self {
+        self.line_numbers = line_numbers;
+        self
+    }
+
+    /// Resolves a fence info string to a grammar: by name or alias first
+    /// (`rust`, `Rust`), then by file extension (`rs`, `py`).
+    fn find_syntax(&self, lang: &str) -> Option<&'static SyntaxReference> {
+        let syntaxes = syntax_set();
+        syntaxes
+            .find_syntax_by_token(lang)
+            .or_else(|| syntaxes.find_syntax_by_extension(&lang.to_lowercase()))
+    }
+
+    /// Grammar for a code block: from the fence's info string if syntect
+    /// knows it, otherwise guessed from the first line (`#!/bin/bash`).
+    fn resolve_syntax(&self, lang: Option<&str>, code: &str) -> Option<&'static SyntaxReference> {
+        lang.filter(|l| !l.is_empty())
+            .and_then(|l| self.find_syntax(l))
+            .or_else(|| syntax_set().find_syntax_by_first_line(code))
+    }
+
+    /// Highlights code into colored runs, one list per line, for output
+    /// formats that aren't HTML. Code without a known grammar comes back as
+    /// a single run per line in the theme's foreground color.
+    pub fn styled_lines(&self, code: &str, lang: Option<&str>) -> Vec<Vec<(Color, String)>> {
+        let foreground = self.theme.settings.foreground.unwrap_or(Color::BLACK);
+        let plain = || {
+            code.lines()
+                .map(|line| vec![(foreground, line.to_string())])
+                .collect()
+        };
+        let Some(syntax) = self.resolve_syntax(lang, code) else {
+            return plain();
+        };
+
+        let mut highlighter = HighlightLines::new(syntax, self.theme);
+        let mut lines = Vec::new();
+        for line in LinesWithEndings::from(code) {
+            let Ok(regions) = highlighter.highlight_line(line, syntax_set()) else {
+                return plain();
+            };
+            lines.push(
+                regions
+                    .into_iter()
+                    .map(|(style, text)| {
+                        (
+                            style.foreground,
+                            text.trim_end_matches(['\n', '\r']).to_string(),
+                        )
+                    })
+                    .filter(|(_, text)| !text.is_empty())
+                    .collect(),
+            );
+        }
+        lines
+    }
+
+    pub fn highlight(&self, code: &str, syntax: &SyntaxReference) -> Result<String, syntect::Error> {
+        let mut highlighter = HighlightLines::new(syntax, self.theme);
+        let mut output = String::new();
+        for line in LinesWithEndings::from(code) {
+            let regions = highlighter.highlight_line(line, syntax_set())?;
+            append_highlighted_html_for_styled_line(&regions, IncludeBackground::No, &mut output)?;
+        }
+        Ok(output)
+    }
+
+    /// Highlighted HTML per line, without line endings, so every line can
+    /// be wrapped on its own.
+    fn highlight_lines(
+        &self,
+        code: &str,
+        syntax: &SyntaxReference,
+    ) -> Result<Vec<String>, syntect::Error> {
+        let mut highlighter = HighlightLines::new(syntax, self.theme);
+        let mut lines = Vec::new();
+        for line in LinesWithEndings::from(code) {
+            let regions: Vec<_> = highlighter
+                .highlight_line(line, syntax_set())?
+                .into_iter()
+                .map(|(style, text)| (style, text.trim_end_matches(['\n', '\r'])))
+                .filter(|(_, text)| !text.is_empty())
+                .collect();
+            let mut output = String::new();
+            append_highlighted_html_for_styled_line(&regions, IncludeBackground::No, &mut output)?;
+            lines.push(output);
+        }
+        Ok(lines)
+    }
+
+    fn write_numbered(
+        &self,
+        output: &mut dyn Write,
+        lang: Option<&str>,
+        code: &str,
+    ) -> io::Result<()> {
+        let lines = match self
+            .resolve_syntax(lang, code)
+            .map(|s| self.highlight_lines(code, s))
+        {
+            Some(Ok(lines)) => lines,
+            _ => code.lines().map(escape).collect(),
+        };
+        for (index, line) in lines.iter().enumerate() {
+            writeln!(
+                output,
+                "<span class=\"line\" id=\"L{n}\" data-line=\"{n}\">{}</span>",
+                line,
+                n = index + 1
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = Vec::new();
+    let _ = html::escape(&mut escaped, text.as_bytes());
+    String::from_utf8_lossy(&escaped).into_owned()
+}
+
+impl SyntaxHighlighterAdapter for Highlighter {
+    fn write_highlighted(
+        &self,
+        output: &mut dyn Write,
+        lang: Option<&str>,
+        code: &str,
+    ) -> io::Result<()> {
+        if self.line_numbers {
+            return self.write_numbered(output, lang, code);
+        }
+        match self
+            .resolve_syntax(lang, code)
+            .map(|s| self.highlight(code, s))
+        {
+            Some(Ok(highlighted)) => output.write_all(highlighted.as_bytes()),
+            _ => html::escape(output, code.as_bytes()),
+        }
+    }
+
+    fn write_pre_tag(
+        &self,
+        output: &mut dyn Write,
+        mut attributes: HashMap<String, String>,
+    ) -> io::Result<()> {
+        if self.line_numbers {
+            attributes.insert("class".to_string(), "numbered".to_string());
+        }
+        html::write_opening_tag(output, "pre", attributes)
+    }
+
+    fn write_code_tag(
+        &self,
+        output: &mut dyn Write,
+        attributes: HashMap<String, String>,
+    ) -> io::Result<()> {
+        html::write_opening_tag(output, "code", attributes)
+    }
+}
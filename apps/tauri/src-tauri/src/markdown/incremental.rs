@@ -0,0 +1,445 @@
+//! Rendering a document block by block, so a view can be updated in place
+//! when its file changes instead of being replaced whole.
+//!
+//! The comrak AST lives in an arena that can't outlive the render, so what's
+//! kept of a render is its top-level blocks: the HTML of each, and a
+//! fingerprint that's the same wherever in the file the block sits. Two
+//! renders are compared by fingerprint, which also catches blocks whose
+//! source didn't change but whose HTML did, like a footnote renumbered by
+//! one added above it.
+
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::Arena;
+use serde::Serialize;
+use std::path::Path;
+
+use super::{
+    formats, render_parts, CodeTheme, DocumentLink, Format, Formatting, FrontMatter, Output,
+    RenderProfile, SourceBlock,
+};
+
+/// A top-level block of a rendered document.
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub html: String,
+    fingerprint: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderedBlocks {
+    pub html: Vec<Block>,
+    pub front_matter: Option<FrontMatter>,
+    pub blocks: Vec<SourceBlock>,
+    pub links: Vec<DocumentLink>,
+}
+
+/// How a render differs from the one before it, in terms of the earlier
+/// render's blocks. Lines are those of the new render, except for removed
+/// blocks.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum BlockChange {
+    /// A new block, after old block `after` (first when `None`). Blocks
+    /// added after the same one come in document order.
+    Added {
+        after: Option<usize>,
+        start_line: usize,
+        end_line: usize,
+        html: String,
+    },
+    /// Old block `index`, gone.
+    Removed {
+        index: usize,
+        start_line: usize,
+        end_line: usize,
+    },
+    /// Old block `index`, now rendered as `html`.
+    Modified {
+        index: usize,
+        start_line: usize,
+        end_line: usize,
+        html: String,
+    },
+    /// Old block `index`, unchanged but `lines` further down the file
+    /// (up, when negative).
+    Moved { index: usize, lines: isize },
+}
+
+/// Formats whose blocks render independently of each other. Other formats
+/// are kept as a single block.
+fn renders_by_block(document: Option<&Path>) -> bool {
+    document.is_none_or(|path| {
+        matches!(
+            Format::from_path(path),
+            Format::Markdown | Format::Notebook | Format::Org | Format::Rst
+        )
+    })
+}
+
+/// Renders a document the way `markdown::render` does, as its top-level
+/// blocks.
+pub fn render(
+    content: &str,
+    profile: &RenderProfile,
+    theme: CodeTheme,
+    document: Option<&Path>,
+) -> Result<RenderedBlocks, String> {
+    let source = formats::load(content, document)?;
+    let by_block = renders_by_block(document);
+    let (html, front_matter, blocks, links) = render_parts(
+        &source,
+        profile,
+        theme,
+        document,
+        Output::View,
+        |arena, root, formatting| split(arena, root, formatting, by_block),
+    );
+    Ok(RenderedBlocks {
+        html,
+        front_matter,
+        blocks,
+        links,
+    })
+}
+
+/// Written before each top-level block so the formatted document can be
+/// cut into blocks. Formatting the blocks one by one instead would number
+/// headings and footnotes per block.
+const MARKER: &str = "<!--arandu:block-->\n";
+
+fn split<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    root: &'a AstNode<'a>,
+    formatting: &Formatting,
+    by_block: bool,
+) -> Vec<Block> {
+    // With raw HTML let through, a block can open an element a later one
+    // closes, and cut apart they'd be sanitized into something else.
+    let raw_html_blocks = formatting.raw_html
+        && root
+            .children()
+            .any(|node| matches!(node.data.borrow().value, NodeValue::HtmlBlock(_)));
+    let (start, end) = lines(root.children());
+    if !by_block || raw_html_blocks {
+        let html = formatting.html(root);
+        return vec![Block::new(start, end, html)];
+    }
+
+    // Footnote definitions are moved to the end of the document when it's
+    // parsed, and render as one list.
+    let mut spans = Vec::new();
+    let mut in_footnotes = false;
+    for node in root.children().collect::<Vec<_>>() {
+        let value = &node.data.borrow().value;
+        if matches!(value, NodeValue::FrontMatter(_)) {
+            continue;
+        }
+        if in_footnotes {
+            if let Some((_, end)) = spans.last_mut() {
+                *end = lines(std::iter::once(node)).1;
+            }
+            continue;
+        }
+        in_footnotes = matches!(value, NodeValue::FootnoteDefinition(_));
+        node.insert_before(arena.alloc(NodeValue::Raw(MARKER.to_string()).into()));
+        spans.push(lines(std::iter::once(node)));
+    }
+
+    let html = formatting.unfinished_html(root);
+    let mut parts = html.split(MARKER);
+    // Anything before the first block, like front matter, renders empty.
+    let lead = parts.next().unwrap_or_default();
+    parts
+        .zip(spans)
+        .enumerate()
+        .map(|(index, (part, (start, end)))| {
+            let part = if index == 0 {
+                format!("{}{}", lead, part)
+            } else {
+                part.to_string()
+            };
+            Block::new(start, end, formatting.finish(part))
+        })
+        .collect()
+}
+
+/// The lines `nodes` span, from the start of the first to the end of the
+/// last.
+fn lines<'a>(nodes: impl Iterator<Item = &'a AstNode<'a>>) -> (usize, usize) {
+    let mut span: Option<(usize, usize)> = None;
+    for node in nodes {
+        let pos = node.data.borrow().sourcepos;
+        if pos.start.line == 0 {
+            continue;
+        }
+        // Blocks closed by a blank line end at column 0 of the line after.
+        let end_line = if pos.end.column == 0 && pos.end.line > pos.start.line {
+            pos.end.line - 1
+        } else {
+            pos.end.line
+        };
+        span = Some(match span {
+            Some((start, end)) => (start.min(pos.start.line), end.max(end_line)),
+            None => (pos.start.line, end_line),
+        });
+    }
+    span.unwrap_or((1, 1))
+}
+
+impl Block {
+    fn new(start_line: usize, end_line: usize, html: String) -> Self {
+        let fingerprint = normalize_attribute(&html, "data-sourcepos", |value| {
+            relative_sourcepos(value, start_line)
+        });
+        // Diagrams are numbered across the document.
+        let fingerprint = normalize_attribute(&fingerprint, "data-diagram", |_| String::new());
+        Block {
+            start_line,
+            end_line,
+            html,
+            fingerprint,
+        }
+    }
+}
+
+/// Rewrites the value of every `name="..."` attribute in `html`.
+fn normalize_attribute(html: &str, name: &str, rewrite: impl Fn(&str) -> String) -> String {
+    let prefix = format!("{}=\"", name);
+    let mut normalized = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(at) = rest.find(&prefix) {
+        let value_start = at + prefix.len();
+        normalized.push_str(&rest[..value_start]);
+        rest = &rest[value_start..];
+        let value_end = rest.find('"').unwrap_or(rest.len());
+        normalized.push_str(&rewrite(&rest[..value_end]));
+        rest = &rest[value_end..];
+    }
+    normalized.push_str(rest);
+    normalized
+}
+
+/// `start:column-end:column` with the lines counted from `base`.
+fn relative_sourcepos(value: &str, base: usize) -> String {
+    let relative = |position: &str| -> Option<String> {
+        let (line, column) = position.split_once(':')?;
+        let line: isize = line.parse().ok()?;
+        Some(format!("{}:{}", line - base as isize, column))
+    };
+    value
+        .split_once('-')
+        .and_then(|(start, end)| Some(format!("{}-{}", relative(start)?, relative(end)?)))
+        .unwrap_or_else(|| value.to_string())
+}
+
+/// Above this many block pairs to compare, a changed stretch of the
+/// document is replaced as a whole rather than matched up block by block.
+const MAX_COMPARISONS: usize = 4_000_000;
+
+/// The changes that turn the blocks of `old` into those of `new`.
+pub fn diff(old: &[Block], new: &[Block]) -> Vec<BlockChange> {
+    let same = |i: usize, j: usize| old[i].fingerprint == new[j].fingerprint;
+    let prefix = (0..old.len().min(new.len()))
+        .take_while(|&i| same(i, i))
+        .count();
+    let suffix = (0..old.len().min(new.len()) - prefix)
+        .take_while(|&k| same(old.len() - 1 - k, new.len() - 1 - k))
+        .count();
+
+    let mut pairs: Vec<(usize, usize)> = (0..prefix).map(|i| (i, i)).collect();
+    pairs.extend(
+        matching(
+            &old[prefix..old.len() - suffix],
+            &new[prefix..new.len() - suffix],
+        )
+        .into_iter()
+        .map(|(i, j)| (i + prefix, j + prefix)),
+    );
+    pairs.extend(
+        (0..suffix)
+            .rev()
+            .map(|k| (old.len() - 1 - k, new.len() - 1 - k)),
+    );
+    // A sentinel pair closes the last stretch.
+    pairs.push((old.len(), new.len()));
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in pairs {
+        // Old blocks i..next_i became new blocks j..next_j.
+        let replaced = (next_i - i).min(next_j - j);
+        for (k, block) in new[j..j + replaced].iter().enumerate() {
+            changes.push(BlockChange::Modified {
+                index: i + k,
+                start_line: block.start_line,
+                end_line: block.end_line,
+                html: block.html.clone(),
+            });
+        }
+        for (index, block) in old.iter().enumerate().take(next_i).skip(i + replaced) {
+            changes.push(BlockChange::Removed {
+                index,
+                start_line: block.start_line,
+                end_line: block.end_line,
+            });
+        }
+        // After the last of the blocks modified above, or else the last
+        // unchanged block before the stretch.
+        let after = (i + replaced).checked_sub(1);
+        for block in &new[j + replaced..next_j] {
+            changes.push(BlockChange::Added {
+                after,
+                start_line: block.start_line,
+                end_line: block.end_line,
+                html: block.html.clone(),
+            });
+        }
+
+        if next_i < old.len() {
+            let lines = new[next_j].start_line as isize - old[next_i].start_line as isize;
+            if lines != 0 {
+                changes.push(BlockChange::Moved {
+                    index: next_i,
+                    lines,
+                });
+            }
+        }
+        (i, j) = (next_i + 1, next_j + 1);
+    }
+    changes
+}
+
+/// The longest run of blocks `old` and `new` have in common, in order, as
+/// index pairs.
+fn matching(old: &[Block], new: &[Block]) -> Vec<(usize, usize)> {
+    if old.is_empty() || new.is_empty() || old.len() * new.len() > MAX_COMPARISONS {
+        return Vec::new();
+    }
+    // lengths[i][j]: the longest common run of old[i..] and new[j..].
+    let width = new.len() + 1;
+    let mut lengths = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i].fingerprint == new[j].fingerprint {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i].fingerprint == new[j].fingerprint {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(content: &str) -> Vec<Block> {
+        render(content, &RenderProfile::default(), CodeTheme::Light, None)
+            .unwrap()
+            .html
+    }
+
+    #[test]
+    fn blocks_join_into_the_whole_render() {
+        let content = "---\ntitle: T\n---\n\n# Title\n\nText[^1] with <kbd>raw</kbd> html.\n\n- [ ] a\n- [x] b\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n```rust\nfn main() {}\n```\n\n> [!NOTE]\n> n\n\n## Title\n\n[^1]: Note.\n\nAfter the note.\n";
+        for raw_html in [false, true] {
+            let profile = RenderProfile {
+                raw_html,
+                ..RenderProfile::default()
+            };
+            let whole = super::super::render(content, &profile, CodeTheme::Light, None).unwrap();
+            let by_block = render(content, &profile, CodeTheme::Light, None).unwrap();
+            assert!(by_block.html.len() > 1);
+            let joined: String = by_block
+                .html
+                .iter()
+                .map(|block| block.html.as_str())
+                .collect();
+            assert_eq!(joined, whole.html, "raw_html: {}", raw_html);
+        }
+    }
+
+    #[test]
+    fn diff_of_a_block_inserted_in_the_middle() {
+        let old = blocks("A\n\nB\n\nC\n");
+        let new = blocks("A\n\nX\n\nB\n\nC\n");
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                BlockChange::Added {
+                    after: Some(0),
+                    start_line: 3,
+                    end_line: 3,
+                    html: new[1].html.clone(),
+                },
+                BlockChange::Moved { index: 1, lines: 2 },
+                BlockChange::Moved { index: 2, lines: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_of_a_block_deleted_from_the_middle() {
+        let old = blocks("A\n\nX\n\nB\n\nC\n");
+        let new = blocks("A\n\nB\n\nC\n");
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                BlockChange::Removed {
+                    index: 1,
+                    start_line: 3,
+                    end_line: 3,
+                },
+                BlockChange::Moved {
+                    index: 2,
+                    lines: -2
+                },
+                BlockChange::Moved {
+                    index: 3,
+                    lines: -2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_of_a_block_edited_in_the_middle() {
+        let old = blocks("A\n\nB\n\nC\n");
+        let new = blocks("A\n\nB, longer\nover two lines\n\nC\n");
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                BlockChange::Modified {
+                    index: 1,
+                    start_line: 3,
+                    end_line: 4,
+                    html: new[1].html.clone(),
+                },
+                BlockChange::Moved { index: 2, lines: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn unchanged_blocks_make_no_changes() {
+        let content = "# A\n\nB[^1]\n\n[^1]: C\n";
+        assert!(diff(&blocks(content), &blocks(content)).is_empty());
+    }
+}
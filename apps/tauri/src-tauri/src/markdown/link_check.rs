@@ -0,0 +1,230 @@
+//! Finds links in local documentation that lead nowhere: relative links
+//! and images whose file is missing, and `#anchors` that match no heading
+//! in the document they point into.
+//!
+//! Links are resolved exactly as the viewer resolves them when following
+//! one, and anchors are compared against the same slugs the outline uses.
+
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::Arena;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::formats::{self, Format};
+use super::links::{self, LinkKind};
+use super::RenderProfile;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Problem {
+    /// The linked file doesn't exist.
+    Missing,
+    /// The file exists but has no heading (or line) the fragment names.
+    Anchor,
+    /// An image whose file doesn't exist.
+    Image,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BrokenLink {
+    /// The document the link is in, as it was found (relative paths stay
+    /// relative, for readable CLI output).
+    pub document: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub problem: Problem,
+    /// The destination as written in the document.
+    pub target: String,
+    pub message: String,
+}
+
+/// Formats a folder check reads. Notebooks are left out because their
+/// line numbers would point into the converted document, not the file.
+fn checked_in_folders(path: &Path) -> bool {
+    matches!(
+        Format::detect(path),
+        Some(Format::Markdown | Format::Org | Format::Rst)
+    )
+}
+
+/// Checks one document, or every document under a folder. Folders are
+/// walked as the workspace tree walks them, so ignored and hidden files
+/// are skipped.
+pub fn check(path: &Path, profile: &RenderProfile) -> Result<Vec<BrokenLink>, String> {
+    let mut checker = Checker {
+        profile,
+        anchors: HashMap::new(),
+    };
+    if !path.is_dir() {
+        return checker.document(path);
+    }
+
+    let mut documents = Vec::new();
+    for entry in formats::walk(path) {
+        let entry = entry.map_err(|e| format!("Failed to read folder: {}", e))?;
+        if entry.file_type().is_some_and(|t| t.is_file()) && checked_in_folders(entry.path()) {
+            documents.push(entry.into_path());
+        }
+    }
+    documents.sort();
+
+    let mut broken = Vec::new();
+    for document in documents {
+        broken.extend(checker.document(&document)?);
+    }
+    Ok(broken)
+}
+
+/// What a fragment can point at in a linked file.
+enum Anchors {
+    /// Heading slugs of a document.
+    Slugs(HashSet<String>),
+    /// `L<n>` and `L<n>-L<m>` in a source file with this many lines.
+    Lines(usize),
+    /// Formats without addressable parts, where any fragment passes.
+    Any,
+}
+
+impl Anchors {
+    fn contains(&self, fragment: &str) -> bool {
+        match self {
+            Anchors::Slugs(slugs) => slugs.contains(fragment),
+            Anchors::Lines(count) => line_range(fragment)
+                .is_some_and(|(start, end)| start >= 1 && start <= end && end <= *count),
+            Anchors::Any => true,
+        }
+    }
+}
+
+fn line_range(fragment: &str) -> Option<(usize, usize)> {
+    let range = fragment.strip_prefix('L')?;
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) => (start, end.strip_prefix('L').unwrap_or(end)),
+        None => (range, range),
+    };
+    Some((start.parse().ok()?, end.parse().ok()?))
+}
+
+struct Checker<'p> {
+    profile: &'p RenderProfile,
+    /// Anchors of every document read so far, by canonical path.
+    anchors: HashMap<PathBuf, Anchors>,
+}
+
+impl Checker<'_> {
+    fn anchors(&mut self, path: &Path) -> &Anchors {
+        if !self.anchors.contains_key(path) {
+            let anchors = self.read_anchors(path);
+            self.anchors.insert(path.to_path_buf(), anchors);
+        }
+        &self.anchors[path]
+    }
+
+    fn read_anchors(&self, path: &Path) -> Anchors {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Anchors::Any;
+        };
+        match Format::from_path(path) {
+            Format::Code => Anchors::Lines(content.lines().count()),
+            Format::Markdown | Format::Notebook | Format::Org | Format::Rst => {
+                let Ok(source) = super::formats::load(&content, Some(path)) else {
+                    return Anchors::Any;
+                };
+                let arena = Arena::new();
+                let root = super::parse(&arena, &source.markdown, self.profile);
+                Anchors::Slugs(
+                    super::headings::extract(root)
+                        .into_iter()
+                        .map(|heading| heading.slug)
+                        .collect(),
+                )
+            }
+            _ => Anchors::Any,
+        }
+    }
+
+    fn document(&mut self, document: &Path) -> Result<Vec<BrokenLink>, String> {
+        let content = std::fs::read_to_string(document)
+            .map_err(|e| format!("Failed to read {}: {}", document.display(), e))?;
+        let canonical = document
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve {}: {}", document.display(), e))?;
+        let source = super::formats::load(&content, Some(&canonical))?;
+
+        let arena = Arena::new();
+        let root = super::parse(&arena, &source.markdown, self.profile);
+
+        let mut broken = Vec::new();
+        for node in root.descendants() {
+            let (url, is_image) = match &node.data.borrow().value {
+                NodeValue::Link(link) => (link.url.clone(), false),
+                NodeValue::Image(link) => (link.url.clone(), true),
+                _ => continue,
+            };
+            let Some(link) = links::resolve(&url, Some(&canonical)) else {
+                continue;
+            };
+
+            let problem = match (&link.path, &link.fragment) {
+                (Some(path), _) if !path.exists() => Some(if is_image {
+                    Problem::Image
+                } else {
+                    Problem::Missing
+                }),
+                (Some(path), Some(fragment))
+                    if link.kind == LinkKind::Markdown && !fragment.is_empty() =>
+                {
+                    let target = path.canonicalize().unwrap_or_else(|_| path.clone());
+                    (!self.anchors(&target).contains(fragment)).then_some(Problem::Anchor)
+                }
+                (None, Some(fragment)) if !fragment.is_empty() => {
+                    (!self.anchors(&canonical).contains(fragment)).then_some(Problem::Anchor)
+                }
+                _ => None,
+            };
+
+            if let Some(problem) = problem {
+                let (line, column) = position(node);
+                broken.push(BrokenLink {
+                    document: document.to_path_buf(),
+                    line,
+                    column,
+                    problem,
+                    message: message(problem, &link),
+                    target: url,
+                });
+            }
+        }
+        Ok(broken)
+    }
+}
+
+fn message(problem: Problem, link: &links::DocumentLink) -> String {
+    let path = link
+        .path
+        .as_deref()
+        .map(|path| path.display().to_string())
+        .unwrap_or_default();
+    let fragment = link.fragment.as_deref().unwrap_or_default();
+    match problem {
+        Problem::Missing => format!("File not found: {}", path),
+        Problem::Image => format!("Image not found: {}", path),
+        Problem::Anchor if link.kind == LinkKind::Anchor => {
+            format!("No heading matches #{}", fragment)
+        }
+        Problem::Anchor if Format::from_path(Path::new(&path)) == Format::Code => {
+            format!("No line {} in {}", fragment, path)
+        }
+        Problem::Anchor => format!("No heading matches #{} in {}", fragment, path),
+    }
+}
+
+/// Where a link starts, falling back to its block for inlines without a
+/// source position.
+fn position<'a>(node: &'a AstNode<'a>) -> (usize, usize) {
+    node.ancestors()
+        .map(|ancestor| ancestor.data.borrow().sourcepos.start)
+        .find(|start| start.line > 0)
+        .map_or((0, 0), |start| (start.line, start.column))
+}
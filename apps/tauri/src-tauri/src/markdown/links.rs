@@ -0,0 +1,93 @@
+use comrak::nodes::{AstNode, NodeValue};
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    /// `#fragment` pointing into the same document.
+    Anchor,
+    /// Another document in a format the app reads, opened in a new tab.
+    Markdown,
+    /// Any other local file, handed to the system's default application.
+    File,
+}
+
+/// A link in the document that the app handles itself instead of letting
+/// the webview navigate.
+#[derive(Debug, Serialize, Clone)]
+pub struct DocumentLink {
+    /// The `href` as written to the rendered HTML.
+    pub href: String,
+    pub kind: LinkKind,
+    /// Absolute path of the target. `None` for anchors.
+    pub path: Option<PathBuf>,
+    /// Percent-decoded fragment, without the leading `#`.
+    pub fragment: Option<String>,
+}
+
+fn decode(fragment: &str) -> String {
+    percent_decode_str(fragment).decode_utf8_lossy().into_owned()
+}
+
+/// Resolves a link destination against the document it appears in.
+///
+/// Returns `None` for links the app leaves alone: anything with a non-file
+/// scheme (`https:`, `mailto:`) and, when the document has no path yet,
+/// every relative link.
+pub fn resolve(url: &str, document: Option<&Path>) -> Option<DocumentLink> {
+    if let Some(fragment) = url.strip_prefix('#') {
+        return Some(DocumentLink {
+            href: url.to_string(),
+            kind: LinkKind::Anchor,
+            path: None,
+            fragment: Some(decode(fragment)),
+        });
+    }
+    if url.is_empty() {
+        return None;
+    }
+
+    let base = Url::from_file_path(document?).ok()?;
+    let target = base.join(url).ok()?;
+    if target.scheme() != "file" {
+        return None;
+    }
+    let path = target.to_file_path().ok()?;
+
+    Some(DocumentLink {
+        href: target.to_string(),
+        kind: if super::formats::is_document(&path) {
+            LinkKind::Markdown
+        } else {
+            LinkKind::File
+        },
+        path: Some(path),
+        fragment: target.fragment().map(decode),
+    })
+}
+
+/// Link rewriter for comrak: local links become absolute `file://` URLs so
+/// the `href` in the HTML matches `DocumentLink::href`.
+pub fn rewriter(document: PathBuf) -> impl Fn(&str) -> String + Send + Sync {
+    move |url: &str| {
+        resolve(url, Some(&document))
+            .map(|link| link.href)
+            .unwrap_or_else(|| url.to_string())
+    }
+}
+
+/// Every distinct local link in the document, in order of appearance.
+pub fn collect<'a>(root: &'a AstNode<'a>, document: Option<&Path>) -> Vec<DocumentLink> {
+    let mut seen = HashSet::new();
+    root.descendants()
+        .filter_map(|node| match &node.data.borrow().value {
+            NodeValue::Link(link) => resolve(&link.url, document),
+            _ => None,
+        })
+        .filter(|link| seen.insert(link.href.clone()))
+        .collect()
+}
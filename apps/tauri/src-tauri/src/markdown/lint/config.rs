@@ -0,0 +1,105 @@
+//! Per-repository lint settings.
+//!
+//! The nearest `.arandu.toml` above a document, up to the root of its git
+//! repository, configures the rules in a `[lint]` table:
+//!
+//! ```toml
+//! [lint]
+//! trailing-whitespace = false    # off
+//! duplicate-heading = "error"    # report as an error
+//! bare-url = "warning"
+//! ```
+//!
+//! Rules that aren't listed keep their default severity.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{Rule, Severity};
+
+pub const FILE_NAME: &str = ".arandu.toml";
+
+#[derive(Debug, Deserialize, Default)]
+struct File {
+    #[serde(default)]
+    lint: HashMap<String, Setting>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(untagged)]
+enum Setting {
+    Enabled(bool),
+    Level(Level),
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+    Off,
+    Warning,
+    Error,
+}
+
+/// Severity of every rule; `None` turns a rule off.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    overrides: HashMap<Rule, Option<Severity>>,
+    /// The file the settings came from, if any.
+    pub path: Option<PathBuf>,
+}
+
+impl Config {
+    pub fn severity(&self, rule: Rule) -> Option<Severity> {
+        self.overrides
+            .get(&rule)
+            .copied()
+            .unwrap_or(Some(rule.default_severity()))
+    }
+}
+
+/// Settings for the document at `document`, or the defaults when no
+/// config file applies. A file that doesn't parse or names an unknown rule
+/// is an error, so a typo doesn't silently turn checks back on.
+pub fn load(document: &Path) -> Result<Config, String> {
+    let Some(path) = find(document) else {
+        return Ok(Config::default());
+    };
+    let raw = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let file: File =
+        toml::from_str(&raw).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+    let mut overrides = HashMap::new();
+    for (name, setting) in file.lint {
+        let rule = Rule::from_name(&name)
+            .ok_or_else(|| format!("Unknown lint rule \"{}\" in {}", name, path.display()))?;
+        let severity = match setting {
+            Setting::Enabled(true) => Some(rule.default_severity()),
+            Setting::Enabled(false) | Setting::Level(Level::Off) => None,
+            Setting::Level(Level::Warning) => Some(Severity::Warning),
+            Setting::Level(Level::Error) => Some(Severity::Error),
+        };
+        overrides.insert(rule, severity);
+    }
+    Ok(Config {
+        overrides,
+        path: Some(path),
+    })
+}
+
+/// Walks up from the document's folder, stopping after the first folder
+/// that holds a `.git` entry.
+fn find(document: &Path) -> Option<PathBuf> {
+    let start = document.canonicalize().ok()?;
+    for dir in start.ancestors().skip(1) {
+        let candidate = dir.join(FILE_NAME);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
@@ -0,0 +1,385 @@
+//! Checks markdown documents for common problems.
+//!
+//! Rules run over the same AST the renderer builds and report line ranges
+//! in the document, so the app can list diagnostics and attach them to the
+//! rendered blocks they fall in. Which rules run, and how severe each one
+//! is, comes from [`config`].
+//!
+//! Adding a rule takes a [`Rule`] variant, its name and a check function.
+
+pub mod config;
+
+use comrak::nodes::{AstNode, NodeValue};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::headings::plain_text;
+use super::RenderProfile;
+pub use config::Config;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    HeadingIncrement,
+    DuplicateHeading,
+    TrailingWhitespace,
+    FenceLanguage,
+    Table,
+    BareUrl,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// 1-based markdown lines, inclusive.
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+struct Finding {
+    message: String,
+    start_line: usize,
+    end_line: usize,
+}
+
+impl Finding {
+    fn new(message: String, start_line: usize, end_line: usize) -> Self {
+        Self {
+            message,
+            start_line,
+            end_line,
+        }
+    }
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Rule::HeadingIncrement,
+        Rule::DuplicateHeading,
+        Rule::TrailingWhitespace,
+        Rule::FenceLanguage,
+        Rule::Table,
+        Rule::BareUrl,
+    ];
+
+    /// Name used in diagnostics and in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::HeadingIncrement => "heading-increment",
+            Rule::DuplicateHeading => "duplicate-heading",
+            Rule::TrailingWhitespace => "trailing-whitespace",
+            Rule::FenceLanguage => "fence-language",
+            Rule::Table => "table",
+            Rule::BareUrl => "bare-url",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+
+    /// A table that renders wrong is an error; the rest are style.
+    pub fn default_severity(self) -> Severity {
+        match self {
+            Rule::Table => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+
+    fn check<'a>(
+        self,
+        root: &'a AstNode<'a>,
+        lines: &[&str],
+        profile: &RenderProfile,
+    ) -> Vec<Finding> {
+        match self {
+            Rule::HeadingIncrement => heading_increment(root),
+            Rule::DuplicateHeading => duplicate_heading(root),
+            Rule::TrailingWhitespace => trailing_whitespace(root, lines),
+            Rule::FenceLanguage => fence_language(root),
+            Rule::Table => table(root, lines, profile),
+            Rule::BareUrl => bare_url(root, lines, profile),
+        }
+    }
+}
+
+/// Runs every enabled rule over a parsed document. `content` is the
+/// markdown `root` was parsed from. Diagnostics come back in line order.
+pub fn check<'a>(
+    root: &'a AstNode<'a>,
+    content: &str,
+    profile: &RenderProfile,
+    config: &Config,
+) -> Vec<Diagnostic> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut diagnostics: Vec<Diagnostic> = Rule::ALL
+        .into_iter()
+        .filter_map(|rule| config.severity(rule).map(|severity| (rule, severity)))
+        .flat_map(|(rule, severity)| {
+            rule.check(root, &lines, profile)
+                .into_iter()
+                .map(move |finding| Diagnostic {
+                    rule: rule.name(),
+                    severity,
+                    message: finding.message,
+                    start_line: finding.start_line,
+                    end_line: finding.end_line,
+                })
+        })
+        .collect();
+
+    diagnostics.sort_by_key(|d| (d.start_line, d.end_line));
+    diagnostics.dedup_by(|a, b| {
+        a.rule == b.rule && a.start_line == b.start_line && a.message == b.message
+    });
+    diagnostics
+}
+
+fn headings<'a>(root: &'a AstNode<'a>) -> impl Iterator<Item = (&'a AstNode<'a>, u8)> {
+    root.descendants()
+        .filter_map(|node| match node.data.borrow().value {
+            NodeValue::Heading(ref heading) => Some((node, heading.level)),
+            _ => None,
+        })
+}
+
+fn lines_of<'a>(node: &'a AstNode<'a>) -> (usize, usize) {
+    let pos = node.data.borrow().sourcepos;
+    (pos.start.line, pos.end.line)
+}
+
+/// `# Title` followed by `### Detail` skips a level.
+fn heading_increment<'a>(root: &'a AstNode<'a>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut previous = None;
+    for (node, level) in headings(root) {
+        if let Some(above) = previous.filter(|&above| level > above + 1) {
+            let (start, end) = lines_of(node);
+            findings.push(Finding::new(
+                format!("Heading level skipped: h{} follows h{}", level, above),
+                start,
+                end,
+            ));
+        }
+        previous = Some(level);
+    }
+    findings
+}
+
+/// Headings with the same text get ambiguous anchors and outline entries.
+fn duplicate_heading<'a>(root: &'a AstNode<'a>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for (node, _) in headings(root) {
+        let text = plain_text(node);
+        let (start, end) = lines_of(node);
+        match seen.get(&text.to_lowercase()) {
+            Some(first) => findings.push(Finding::new(
+                format!(
+                    "Duplicate heading \"{}\", first used on line {}",
+                    text, first
+                ),
+                start,
+                end,
+            )),
+            None => {
+                seen.insert(text.to_lowercase(), start);
+            }
+        }
+    }
+    findings
+}
+
+/// Any trailing spaces or tabs, except the two spaces of a hard line break.
+/// Code blocks and front matter are left alone, since whitespace in them
+/// is content.
+fn trailing_whitespace<'a>(root: &'a AstNode<'a>, lines: &[&str]) -> Vec<Finding> {
+    let verbatim: Vec<(usize, usize)> = root
+        .descendants()
+        .filter(|node| {
+            matches!(
+                node.data.borrow().value,
+                NodeValue::CodeBlock(_) | NodeValue::FrontMatter(_)
+            )
+        })
+        .map(lines_of)
+        .collect();
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| {
+            !verbatim
+                .iter()
+                .any(|&(start, end)| (start..=end).contains(&(index + 1)))
+        })
+        .filter_map(|(index, line)| {
+            let text = line.trim_end_matches([' ', '\t']);
+            let trailing = &line[text.len()..];
+            let line_break = trailing == "  " && !text.trim().is_empty();
+            (!trailing.is_empty() && !line_break)
+                .then(|| Finding::new("Trailing whitespace".to_string(), index + 1, index + 1))
+        })
+        .collect()
+}
+
+/// Fenced code without a language is neither highlighted nor labeled.
+fn fence_language<'a>(root: &'a AstNode<'a>) -> Vec<Finding> {
+    root.descendants()
+        .filter(|node| match node.data.borrow().value {
+            NodeValue::CodeBlock(ref block) => block.fenced && block.info.trim().is_empty(),
+            _ => false,
+        })
+        .map(|node| {
+            let (start, end) = lines_of(node);
+            Finding::new("Code fence has no language".to_string(), start, end)
+        })
+        .collect()
+}
+
+/// The text of a node's first line, from the column it starts at, so block
+/// quote and list markers in front of it are left out.
+fn source_line<'l>(node: &AstNode<'_>, lines: &[&'l str]) -> Option<&'l str> {
+    let pos = node.data.borrow().sourcepos;
+    let line = lines.get(pos.start.line.checked_sub(1)?)?;
+    line.get(pos.start.column.saturating_sub(1)..)
+}
+
+/// Cells in a table row as GFM splits it: on unescaped pipes, not counting
+/// the optional ones at either end.
+fn count_cells(row: &str) -> usize {
+    let row = row.trim();
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let mut chars = row.chars();
+    let mut cells = 1;
+    let mut trailing_pipe = false;
+    while let Some(c) = chars.next() {
+        trailing_pipe = c == '|';
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '|' => cells += 1,
+            _ => {}
+        }
+    }
+    if trailing_pipe {
+        cells - 1
+    } else {
+        cells
+    }
+}
+
+/// Rows whose cell count differs from the header's (extra cells are
+/// dropped when rendering), and pipe rows that didn't become a table at
+/// all, usually because the delimiter row is missing or malformed.
+fn table<'a>(root: &'a AstNode<'a>, lines: &[&str], profile: &RenderProfile) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for node in root.descendants() {
+        match node.data.borrow().value {
+            NodeValue::Table(ref table) => {
+                let columns = table.alignments.len();
+                for row in node.children() {
+                    let Some(text) = source_line(row, lines) else {
+                        continue;
+                    };
+                    let cells = count_cells(text);
+                    if cells != columns {
+                        let (start, end) = lines_of(row);
+                        findings.push(Finding::new(
+                            format!(
+                                "Table row has {} cell{}, the header has {}",
+                                cells,
+                                if cells == 1 { "" } else { "s" },
+                                columns
+                            ),
+                            start,
+                            end,
+                        ));
+                    }
+                }
+            }
+            NodeValue::Paragraph if profile.table => {
+                let Some(text) = source_line(node, lines) else {
+                    continue;
+                };
+                if text.trim_start().starts_with('|') && count_cells(text) >= 2 {
+                    let (start, end) = lines_of(node);
+                    findings.push(Finding::new(
+                        "Looks like a table, but isn't one: the delimiter row (| --- |) under the header is missing or malformed".to_string(),
+                        start,
+                        end,
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+    findings
+}
+
+/// The block a node belongs to, for inlines, which don't carry reliable
+/// source positions of their own.
+fn block<'a>(node: &'a AstNode<'a>) -> &'a AstNode<'a> {
+    node.ancestors()
+        .find(|ancestor| ancestor.data.borrow().value.block())
+        .unwrap_or(node)
+}
+
+fn in_link<'a>(node: &'a AstNode<'a>) -> bool {
+    node.ancestors()
+        .any(|ancestor| matches!(ancestor.data.borrow().value, NodeValue::Link(_)))
+}
+
+/// Where `url` appears written out bare in a block: not in `<…>`, not the
+/// text or target of a `[…](…)` link.
+fn bare_line<'a>(node: &'a AstNode<'a>, lines: &[&str], url: &str) -> Option<usize> {
+    let (start, end) = lines_of(block(node));
+    (start..=end).find(|&number| {
+        let Some(line) = lines.get(number - 1) else {
+            return false;
+        };
+        line.match_indices(url)
+            .any(|(at, _)| !matches!(line[..at].chars().next_back(), Some('<' | '(' | '[' | '"')))
+    })
+}
+
+/// URLs pasted as plain text. With autolinks on they still turn into
+/// links, but only in renderers that support the extension.
+fn bare_url<'a>(root: &'a AstNode<'a>, lines: &[&str], profile: &RenderProfile) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for node in root.descendants() {
+        let urls: Vec<String> = match node.data.borrow().value {
+            NodeValue::Link(ref link) if profile.autolink => {
+                let text = plain_text(node);
+                let autolinked = link.url == text
+                    || link.url.strip_prefix("mailto:") == Some(text.as_str())
+                    || link.url.strip_prefix("http://") == Some(text.as_str());
+                if autolinked {
+                    vec![text]
+                } else {
+                    Vec::new()
+                }
+            }
+            NodeValue::Text(ref text) if !profile.autolink && !in_link(node) => text
+                .split_whitespace()
+                .filter(|word| word.starts_with("http://") || word.starts_with("https://"))
+                .map(|word| word.trim_end_matches(['.', ',', ';', ':', ')']).to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+        for url in urls {
+            if let Some(line) = bare_line(node, lines, &url) {
+                findings.push(Finding::new(format!("Bare URL: {}", url), line, line));
+            }
+        }
+    }
+    findings
+}
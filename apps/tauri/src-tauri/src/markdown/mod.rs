@@ -0,0 +1,220 @@
+//! Markdown rendering pipeline.
+//!
+//! Documents are parsed into a comrak AST once, inspected for metadata and
+//! then formatted to HTML, so everything the frontend needs comes out of a
+//! single `render_markdown` call.
+
+pub mod assets;
+pub mod diagrams;
+pub mod export;
+pub mod formats;
+pub mod front_matter;
+pub mod headings;
+pub mod highlight;
+pub mod incremental;
+pub mod link_check;
+pub mod links;
+pub mod lint;
+pub mod outline;
+pub mod pdf;
+pub mod profile;
+pub mod review;
+pub mod sanitize;
+pub mod sourcepos;
+pub mod tasks;
+
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::{format_html_with_plugins, parse_document, Arena, Options, Plugins};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+
+pub use formats::{Format, Source};
+pub use front_matter::FrontMatter;
+pub use headings::Heading;
+pub use highlight::CodeTheme;
+pub use links::DocumentLink;
+pub use outline::Outline;
+pub use profile::RenderProfile;
+pub use review::ReviewNote;
+pub use sourcepos::SourceBlock;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RenderedMarkdown {
+    pub html: String,
+    pub front_matter: Option<FrontMatter>,
+    pub blocks: Vec<SourceBlock>,
+    pub links: Vec<DocumentLink>,
+}
+
+fn options(profile: &RenderProfile, front_matter_delimiter: Option<&str>) -> Options<'static> {
+    let mut options = Options::default();
+    profile.apply(&mut options);
+    options.extension.front_matter_delimiter = front_matter_delimiter.map(String::from);
+    options.render.sourcepos = true;
+    options
+}
+
+/// Parses a document with the same options `render` uses, so AST consumers
+/// see exactly the structure that ends up on screen.
+pub fn parse<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    content: &str,
+    profile: &RenderProfile,
+) -> &'a AstNode<'a> {
+    let options = options(profile, front_matter::detect_delimiter(content));
+    parse_document(arena, content, &options)
+}
+
+/// Where a render is shown, which decides what local references become.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    /// The app's webview. Local images go through the asset protocol and
+    /// relative links are resolved to the files they point to.
+    View,
+    /// A file that has to stand on its own, like an export. Local images
+    /// are embedded as `data:` URIs, and links are kept as written so they
+    /// don't point into the machine the file was made on.
+    Standalone,
+}
+
+/// Renders a document to HTML, converting it first if `document` names a
+/// format other than markdown. Relative links and image sources are
+/// resolved against `document`.
+pub fn render(
+    content: &str,
+    profile: &RenderProfile,
+    theme: CodeTheme,
+    document: Option<&Path>,
+) -> Result<RenderedMarkdown, String> {
+    let source = formats::load(content, document)?;
+    Ok(render_with(&source, profile, theme, document, Output::View))
+}
+
+pub fn render_with(
+    source: &Source,
+    profile: &RenderProfile,
+    theme: CodeTheme,
+    document: Option<&Path>,
+    output: Output,
+) -> RenderedMarkdown {
+    let (html, front_matter, blocks, links) =
+        render_parts(source, profile, theme, document, output, |_, root, formatting| {
+            formatting.html(root)
+        });
+    RenderedMarkdown {
+        html,
+        front_matter,
+        blocks,
+        links,
+    }
+}
+
+/// The options and plugins a parsed document is formatted with, and the
+/// diagrams its placeholders stand for.
+pub(crate) struct Formatting<'p> {
+    pub options: Options<'static>,
+    pub plugins: Plugins<'p>,
+    pub diagrams: Vec<String>,
+    pub raw_html: bool,
+    pub output: Output,
+}
+
+impl Formatting<'_> {
+    /// Formats `node` and everything below it.
+    pub fn html<'a>(&self, node: &'a AstNode<'a>) -> String {
+        self.finish(self.unfinished_html(node))
+    }
+
+    /// Formats `node` and everything below it, without [`Self::finish`].
+    pub fn unfinished_html<'a>(&self, node: &'a AstNode<'a>) -> String {
+        let mut html = Vec::new();
+        if let Err(e) = format_html_with_plugins(node, &self.options, &mut html, &self.plugins) {
+            eprintln!("Failed to format markdown: {}", e);
+        }
+        String::from_utf8_lossy(&html).into_owned()
+    }
+
+    /// Sanitizes formatted HTML if raw HTML is let through, then puts the
+    /// diagrams in.
+    pub fn finish(&self, mut html: String) -> String {
+        if self.raw_html {
+            html = sanitize::clean_rendered(&html, self.output);
+        }
+        diagrams::splice(html, &self.diagrams)
+    }
+}
+
+/// Parses and prepares a document the way every render does, then hands
+/// the tree to `format` for the HTML.
+pub(crate) fn render_parts<T>(
+    source: &Source,
+    profile: &RenderProfile,
+    theme: CodeTheme,
+    document: Option<&Path>,
+    output: Output,
+    format: impl for<'a> FnOnce(&'a Arena<AstNode<'a>>, &'a AstNode<'a>, &Formatting) -> T,
+) -> (T, Option<FrontMatter>, Vec<SourceBlock>, Vec<DocumentLink>) {
+    let content = source.markdown.as_str();
+    let delimiter = front_matter::detect_delimiter(content);
+    let mut options = options(profile, delimiter);
+    if let Some(document) = document {
+        let document = document.to_path_buf();
+        match output {
+            Output::View => {
+                options.extension.link_url_rewriter =
+                    Some(Arc::new(links::rewriter(document.clone())));
+                options.extension.image_url_rewriter = Some(Arc::new(assets::rewriter(document)));
+            }
+            Output::Standalone => {
+                options.extension.image_url_rewriter =
+                    Some(Arc::new(assets::embedding_rewriter(document)));
+            }
+        }
+    }
+
+    let highlighter = highlight::Highlighter::new(theme).with_line_numbers(source.line_numbers);
+    let mut plugins = Plugins::default();
+    plugins.render.codefence_syntax_highlighter = Some(&highlighter);
+
+    let arena = Arena::new();
+    let root = parse_document(&arena, content, &options);
+
+    if profile.raw_html {
+        defuse_raw_html(root);
+    }
+    let front_matter = delimiter.and_then(|d| find_front_matter(root, d));
+    let diagrams = diagrams::replace(root);
+    formats::embed(root, &source.embeds);
+    let blocks = sourcepos::collect(root);
+    let links = links::collect(root, document);
+
+    let formatting = Formatting {
+        options,
+        plugins,
+        diagrams,
+        raw_html: profile.raw_html,
+        output,
+    };
+    let html = format(&arena, root, &formatting);
+    (html, front_matter, blocks, links)
+}
+
+/// Renames the attributes only the renderer may write in the document's
+/// own HTML, before it's formatted alongside the renderer's.
+fn defuse_raw_html<'a>(root: &'a AstNode<'a>) {
+    for node in root.descendants() {
+        match &mut node.data.borrow_mut().value {
+            NodeValue::HtmlBlock(block) => block.literal = sanitize::defuse(&block.literal),
+            NodeValue::HtmlInline(literal) => *literal = sanitize::defuse(literal),
+            _ => {}
+        }
+    }
+}
+
+fn find_front_matter<'a>(root: &'a AstNode<'a>, delimiter: &str) -> Option<FrontMatter> {
+    root.children().find_map(|node| match &node.data.borrow().value {
+        NodeValue::FrontMatter(literal) => Some(front_matter::parse(literal, delimiter)),
+        _ => None,
+    })
+}
@@ -0,0 +1,140 @@
+use comrak::nodes::{AstNode, NodeValue};
+use serde::Serialize;
+
+use super::headings::{self, Heading};
+
+/// Counters gathered over a range of markdown lines.
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct SectionStats {
+    pub word_count: usize,
+    pub tasks_total: usize,
+    pub tasks_done: usize,
+    pub unresolved_comments: usize,
+}
+
+/// A heading together with everything up to the next heading of the same
+/// or a higher level. Stats include nested subsections.
+#[derive(Debug, Serialize, Clone)]
+pub struct Section {
+    #[serde(flatten)]
+    pub heading: Heading,
+    pub start_line: usize,
+    pub end_line: usize,
+    #[serde(flatten)]
+    pub stats: SectionStats,
+    pub children: Vec<Section>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Outline {
+    pub sections: Vec<Section>,
+    /// Totals for the whole document, including text before the first
+    /// heading.
+    #[serde(flatten)]
+    pub stats: SectionStats,
+}
+
+/// Something countable that sits on a single source line.
+enum Entry {
+    Words(usize),
+    Task { done: bool },
+    Comment,
+}
+
+/// Builds the section tree. `comment_lines` holds the first source line of
+/// each unresolved review comment.
+pub fn build<'a>(root: &'a AstNode<'a>, content: &str, comment_lines: &[usize]) -> Outline {
+    let mut entries = collect_entries(root);
+    entries.extend(comment_lines.iter().map(|&line| (line, Entry::Comment)));
+
+    let last_line = content.lines().count().max(1);
+    let headings = headings::extract(root);
+
+    let mut sections = Vec::new();
+    for (i, heading) in headings.iter().enumerate() {
+        let end_line = headings[i + 1..]
+            .iter()
+            .find(|next| next.level <= heading.level)
+            .map(|next| next.line.saturating_sub(1))
+            .unwrap_or(last_line);
+        sections.push(Section {
+            heading: heading.clone(),
+            start_line: heading.line,
+            end_line,
+            stats: stats_between(&entries, heading.line, end_line),
+            children: Vec::new(),
+        });
+    }
+
+    Outline {
+        sections: nest(sections),
+        stats: stats_between(&entries, 1, usize::MAX),
+    }
+}
+
+fn collect_entries<'a>(root: &'a AstNode<'a>) -> Vec<(usize, Entry)> {
+    let mut entries = Vec::new();
+    for node in root.descendants() {
+        let ast = node.data.borrow();
+        let line = ast.sourcepos.start.line;
+        match ast.value {
+            NodeValue::Paragraph | NodeValue::Heading(_) | NodeValue::TableCell => {
+                let words = headings::plain_text(node).split_whitespace().count();
+                entries.push((line, Entry::Words(words)));
+            }
+            NodeValue::TaskItem(symbol) => {
+                let done = symbol.is_some_and(|c| !c.is_whitespace());
+                entries.push((line, Entry::Task { done }));
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+fn stats_between(entries: &[(usize, Entry)], start: usize, end: usize) -> SectionStats {
+    let mut stats = SectionStats::default();
+    for (_, entry) in entries.iter().filter(|(line, _)| (start..=end).contains(line)) {
+        match entry {
+            Entry::Words(words) => stats.word_count += words,
+            Entry::Task { done } => {
+                stats.tasks_total += 1;
+                if *done {
+                    stats.tasks_done += 1;
+                }
+            }
+            Entry::Comment => stats.unresolved_comments += 1,
+        }
+    }
+    stats
+}
+
+/// Turns the flat, document-ordered list into a tree by heading level.
+fn nest(flat: Vec<Section>) -> Vec<Section> {
+    let mut roots: Vec<Section> = Vec::new();
+    let mut stack: Vec<Section> = Vec::new();
+
+    for section in flat {
+        while stack
+            .last()
+            .is_some_and(|open| open.heading.level >= section.heading.level)
+        {
+            close(&mut stack, &mut roots);
+        }
+        stack.push(section);
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots);
+    }
+
+    roots
+}
+
+fn close(stack: &mut Vec<Section>, roots: &mut Vec<Section>) {
+    if let Some(done) = stack.pop() {
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(done),
+            None => roots.push(done),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! PDF outline (the bookmarks sidebar) built from the document headings.
+//!
+//! printpdf only knows one bookmark per page with no nesting, so the
+//! outline is added afterwards by editing the finished file with lopdf.
+//! The same pass compresses the page contents, which printpdf writes out
+//! uncompressed.
+
+use printpdf::lopdf::{Dictionary, Document, Object, ObjectId, StringFormat};
+
+/// A heading as it was laid out.
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    pub title: String,
+    pub level: u8,
+    /// Zero-based page index.
+    pub page: usize,
+    /// Top of the heading in PDF user space (points from the page bottom).
+    pub top: f32,
+}
+
+struct Entry {
+    id: ObjectId,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+/// PDF text strings outside ASCII have to be UTF-16BE with a byte order mark.
+fn text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        return Object::string_literal(text);
+    }
+    let mut bytes = vec![0xFE, 0xFF];
+    for unit in text.encode_utf16() {
+        bytes.extend_from_slice(&unit.to_be_bytes());
+    }
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+fn reference(id: ObjectId) -> Object {
+    Object::Reference(id)
+}
+
+/// Adds a nested outline for `bookmarks` to `pdf` and opens the viewer with
+/// the outline panel showing.
+pub fn add_outline(pdf: Vec<u8>, bookmarks: &[Bookmark]) -> Result<Vec<u8>, String> {
+    let mut doc = Document::load_mem(&pdf).map_err(|e| e.to_string())?;
+    if !bookmarks.is_empty() {
+        write_outline(&mut doc, bookmarks)?;
+    }
+    doc.compress();
+
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+fn write_outline(doc: &mut Document, bookmarks: &[Bookmark]) -> Result<(), String> {
+    let pages = doc.get_pages();
+    let root_id = doc.new_object_id();
+
+    // Same nesting rule as the outline sidebar: a heading belongs to the
+    // closest preceding heading of a lower level.
+    let mut entries: Vec<Entry> = Vec::with_capacity(bookmarks.len());
+    let mut top_level = Vec::new();
+    let mut stack: Vec<usize> = Vec::new();
+    for (index, bookmark) in bookmarks.iter().enumerate() {
+        while stack
+            .last()
+            .is_some_and(|&open| bookmarks[open].level >= bookmark.level)
+        {
+            stack.pop();
+        }
+        let parent = stack.last().copied();
+        match parent {
+            Some(parent) => entries[parent].children.push(index),
+            None => top_level.push(index),
+        }
+        entries.push(Entry {
+            id: doc.new_object_id(),
+            parent,
+            children: Vec::new(),
+        });
+        stack.push(index);
+    }
+
+    fn descendants(entries: &[Entry], index: usize) -> i64 {
+        entries[index]
+            .children
+            .iter()
+            .map(|&child| 1 + descendants(entries, child))
+            .sum()
+    }
+
+    let siblings_of = |entry: &Entry| match entry.parent {
+        Some(parent) => &entries[parent].children,
+        None => &top_level,
+    };
+
+    for (index, (entry, bookmark)) in entries.iter().zip(bookmarks).enumerate() {
+        let Some(&page_id) = pages.get(&(bookmark.page as u32 + 1)) else {
+            continue;
+        };
+        let mut item = Dictionary::new();
+        item.set("Title", text_string(&bookmark.title));
+        item.set(
+            "Parent",
+            reference(entry.parent.map_or(root_id, |p| entries[p].id)),
+        );
+        item.set(
+            "Dest",
+            vec![
+                reference(page_id),
+                "XYZ".into(),
+                Object::Null,
+                Object::Real(bookmark.top),
+                Object::Null,
+            ],
+        );
+
+        let siblings = siblings_of(entry);
+        let position = siblings
+            .iter()
+            .position(|&s| s == index)
+            .unwrap_or_default();
+        if position > 0 {
+            item.set("Prev", reference(entries[siblings[position - 1]].id));
+        }
+        if let Some(&next) = siblings.get(position + 1) {
+            item.set("Next", reference(entries[next].id));
+        }
+        if let (Some(&first), Some(&last)) = (entry.children.first(), entry.children.last()) {
+            item.set("First", reference(entries[first].id));
+            item.set("Last", reference(entries[last].id));
+            item.set("Count", descendants(&entries, index));
+        }
+        doc.objects.insert(entry.id, Object::Dictionary(item));
+    }
+
+    let mut root = Dictionary::new();
+    root.set("Type", "Outlines");
+    if let (Some(&first), Some(&last)) = (top_level.first(), top_level.last()) {
+        root.set("First", reference(entries[first].id));
+        root.set("Last", reference(entries[last].id));
+    }
+    root.set("Count", entries.len() as i64);
+    doc.objects.insert(root_id, Object::Dictionary(root));
+
+    let catalog = doc.catalog_mut().map_err(|e| e.to_string())?;
+    catalog.set("Outlines", reference(root_id));
+    catalog.set("PageMode", "UseOutlines");
+    Ok(())
+}
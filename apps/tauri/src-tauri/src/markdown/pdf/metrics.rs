@@ -0,0 +1,61 @@
+//! Glyph widths for the standard PDF fonts, taken from the Adobe AFM files.
+//!
+//! The built-in fonts aren't embedded, so there's no font file to measure;
+//! line breaking works from these tables instead. Only printable ASCII is
+//! listed. Other characters are measured as an average-width glyph, which
+//! is close enough for the accented letters the WinAnsi encoding has.
+
+/// Helvetica (and Helvetica-Oblique), characters 32 to 126.
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278,
+    278, // space to /
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, // 0 to ?
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, // @ to O
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, // P to _
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, // ` to o
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // p to ~
+];
+
+/// Helvetica-Bold (and Helvetica-BoldOblique), characters 32 to 126.
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278,
+    278, // space to /
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, // 0 to ?
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778, // @ to O
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556, // P to _
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611, // ` to o
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584, // p to ~
+];
+
+/// Every Courier glyph has the same width.
+const COURIER: u16 = 600;
+
+const FALLBACK: u16 = 556;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Face {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+    Mono,
+}
+
+fn glyph_width(face: Face, c: char) -> u16 {
+    let table = match face {
+        Face::Mono => return COURIER,
+        Face::Regular | Face::Italic => &HELVETICA,
+        Face::Bold | Face::BoldItalic => &HELVETICA_BOLD,
+    };
+    (c as usize)
+        .checked_sub(32)
+        .and_then(|index| table.get(index))
+        .copied()
+        .unwrap_or(FALLBACK)
+}
+
+/// Width of `text` in points when set at `size`.
+pub fn text_width(text: &str, face: Face, size: f32) -> f32 {
+    let units: u32 = text.chars().map(|c| u32::from(glyph_width(face, c))).sum();
+    units as f32 * size / 1000.0
+}
@@ -0,0 +1,1342 @@
+//! PDF export.
+//!
+//! The comrak AST is laid out directly onto A4 pages with the standard PDF
+//! fonts. There's no webview or HTML engine involved, so the export works
+//! the same from the command line as from the app.
+
+mod bookmarks;
+mod metrics;
+
+use comrak::nodes::{
+    AlertType, AstNode, ListDelimType, ListType, NodeList, NodeValue, TableAlignment,
+};
+use comrak::Arena;
+use printpdf::path::PaintMode;
+use printpdf::{
+    image_crate, BuiltinFont, Color, Image, ImageTransform, IndirectFontRef, Line as PathLine, Mm,
+    PdfDocument, PdfDocumentReference, PdfLayerReference, Point, Pt, Rect, Rgb,
+};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use self::bookmarks::Bookmark;
+use self::metrics::{text_width, Face};
+use super::highlight::{CodeTheme, Highlighter};
+use super::review::{self, ReviewNote};
+use super::{headings, links, parse, sourcepos, RenderProfile, SourceBlock};
+
+// Everything below is in points, measured from the top left of the page.
+const PAGE_WIDTH: f32 = 595.28;
+const PAGE_HEIGHT: f32 = 841.89;
+const MARGIN: f32 = 56.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+const CONTENT_HEIGHT: f32 = PAGE_HEIGHT - 2.0 * MARGIN;
+
+const BODY_SIZE: f32 = 10.5;
+const TABLE_SIZE: f32 = 9.5;
+const CODE_SIZE: f32 = 8.5;
+const FOOTER_SIZE: f32 = 8.5;
+const MARKER_SIZE: f32 = 7.5;
+const HEADING_SIZES: [f32; 6] = [20.0, 16.0, 13.5, 12.0, 10.5, 10.5];
+const LINE_SPACING: f32 = 1.45;
+const CODE_LINE_SPACING: f32 = 1.4;
+
+const BLOCK_GAP: f32 = 7.0;
+const LIST_INDENT: f32 = 18.0;
+const QUOTE_INDENT: f32 = 14.0;
+const CODE_PADDING: f32 = 6.0;
+const CELL_PADDING: f32 = 5.0;
+
+type Ink = (f32, f32, f32);
+
+const TEXT: Ink = (0.12, 0.14, 0.16);
+const MUTED: Ink = (0.35, 0.39, 0.43);
+const LINK: Ink = (0.04, 0.41, 0.85);
+const BORDER: Ink = (0.82, 0.84, 0.87);
+const CODE_BACKGROUND: Ink = (0.965, 0.972, 0.98);
+const HEADER_BACKGROUND: Ink = (0.95, 0.96, 0.97);
+const WHITE: Ink = (1.0, 1.0, 1.0);
+const OPEN: Ink = (0.82, 0.14, 0.18);
+const RESOLVED: Ink = (0.1, 0.5, 0.22);
+
+/// Characters outside ASCII and Latin-1 that the WinAnsi encoding of the
+/// standard fonts still has a glyph for.
+const WIN_ANSI_EXTRAS: &str = "€‚ƒ„…†‡ˆ‰Š‹ŒŽ‘’“”•–—˜™š›œžŸ";
+
+fn mm(points: f32) -> Mm {
+    Pt(points).into()
+}
+
+fn color((r, g, b): Ink) -> Color {
+    Color::Rgb(Rgb::new(r, g, b, None))
+}
+
+/// The standard fonts can only show WinAnsi characters; anything else
+/// would silently disappear from the output, so it's made visible as `?`
+/// and added to `replaced`.
+fn win_ansi(text: &str, replaced: &mut BTreeSet<char>) -> String {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c,
+            '\t' => ' ',
+            c if WIN_ANSI_EXTRAS.contains(c) => c,
+            _ => {
+                replaced.insert(c);
+                '?'
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    code: bool,
+    link: bool,
+    strike: bool,
+}
+
+impl Style {
+    fn face(self) -> Face {
+        match (self.code, self.bold, self.italic) {
+            (true, _, _) => Face::Mono,
+            (false, true, true) => Face::BoldItalic,
+            (false, true, false) => Face::Bold,
+            (false, false, true) => Face::Italic,
+            (false, false, false) => Face::Regular,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    /// A piece of text without whitespace. `space` is whether whitespace
+    /// came before it; pieces without it stick to the previous one.
+    Text {
+        text: String,
+        style: Style,
+        space: bool,
+    },
+    Break,
+    Image {
+        url: String,
+        alt: String,
+    },
+}
+
+/// Flattens inline nodes into tokens for line breaking.
+#[derive(Default)]
+struct Inlines {
+    tokens: Vec<Token>,
+    space: bool,
+}
+
+impl Inlines {
+    fn text(&mut self, text: &str, style: Style) {
+        for (index, word) in text.split(char::is_whitespace).enumerate() {
+            if index > 0 {
+                self.space = true;
+            }
+            if !word.is_empty() {
+                let space = std::mem::take(&mut self.space);
+                self.tokens.push(Token::Text {
+                    text: word.to_string(),
+                    style,
+                    space,
+                });
+            }
+        }
+    }
+
+    fn push(&mut self, token: Token) {
+        self.space = false;
+        self.tokens.push(token);
+    }
+
+    fn collect<'a>(&mut self, node: &'a AstNode<'a>, style: Style) {
+        for child in node.children() {
+            match &child.data.borrow().value {
+                NodeValue::Text(text) => self.text(text, style),
+                NodeValue::Code(code) => self.text(
+                    &code.literal,
+                    Style {
+                        code: true,
+                        ..style
+                    },
+                ),
+                NodeValue::Math(math) => self.text(
+                    &math.literal,
+                    Style {
+                        code: true,
+                        ..style
+                    },
+                ),
+                NodeValue::SoftBreak => self.space = true,
+                NodeValue::LineBreak => self.push(Token::Break),
+                NodeValue::Emph => self.collect(
+                    child,
+                    Style {
+                        italic: true,
+                        ..style
+                    },
+                ),
+                NodeValue::Strong => self.collect(
+                    child,
+                    Style {
+                        bold: true,
+                        ..style
+                    },
+                ),
+                NodeValue::Strikethrough => self.collect(
+                    child,
+                    Style {
+                        strike: true,
+                        ..style
+                    },
+                ),
+                NodeValue::Link(_) | NodeValue::WikiLink(_) => self.collect(
+                    child,
+                    Style {
+                        link: true,
+                        ..style
+                    },
+                ),
+                NodeValue::Image(link) => {
+                    let mut alt = Inlines::default();
+                    alt.collect(child, style);
+                    self.push(Token::Image {
+                        url: link.url.clone(),
+                        alt: plain_text(&alt.tokens),
+                    });
+                }
+                NodeValue::FootnoteReference(reference) => {
+                    self.text(&format!("[{}]", reference.name), style)
+                }
+                NodeValue::HtmlInline(_) => {}
+                _ => self.collect(child, style),
+            }
+        }
+    }
+}
+
+fn inlines<'a>(node: &'a AstNode<'a>, style: Style) -> Vec<Token> {
+    let mut inlines = Inlines::default();
+    inlines.collect(node, style);
+    inlines.tokens
+}
+
+fn plain_text(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    for token in tokens {
+        if let Token::Text {
+            text: word, space, ..
+        } = token
+        {
+            if *space && !text.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(word);
+        }
+    }
+    text
+}
+
+/// A stretch of one style within a laid out line. `x` is relative to the
+/// start of the line.
+#[derive(Debug, Clone)]
+struct Run {
+    text: String,
+    style: Style,
+    x: f32,
+    width: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Line {
+    runs: Vec<Run>,
+    width: f32,
+}
+
+impl Line {
+    fn place(&mut self, text: &str, style: Style, space: bool, size: f32) {
+        let face = style.face();
+        let space_width = if space && !self.runs.is_empty() {
+            text_width(" ", face, size)
+        } else {
+            0.0
+        };
+        let width = text_width(text, face, size);
+        match self.runs.last_mut() {
+            Some(run) if run.style == style => {
+                if space_width > 0.0 {
+                    run.text.push(' ');
+                }
+                run.text.push_str(text);
+                run.width += space_width + width;
+            }
+            _ => self.runs.push(Run {
+                text: text.to_string(),
+                style,
+                x: self.width + space_width,
+                width,
+            }),
+        }
+        self.width += space_width + width;
+    }
+}
+
+/// Greedy line breaking. Words that are glued together (`**bold**,`) move
+/// to the next line as one; a word wider than the whole line is split
+/// between characters.
+fn wrap(tokens: &[Token], width: f32, size: f32) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut line = Line::default();
+    let mut index = 0;
+
+    while index < tokens.len() {
+        let space = match &tokens[index] {
+            Token::Text { space, .. } => *space,
+            Token::Break => {
+                lines.push(std::mem::take(&mut line));
+                index += 1;
+                continue;
+            }
+            Token::Image { .. } => {
+                index += 1;
+                continue;
+            }
+        };
+
+        let mut end = index + 1;
+        while matches!(tokens.get(end), Some(Token::Text { space: false, .. })) {
+            end += 1;
+        }
+        let group: Vec<(&str, Style)> = tokens[index..end]
+            .iter()
+            .filter_map(|token| match token {
+                Token::Text { text, style, .. } => Some((text.as_str(), *style)),
+                _ => None,
+            })
+            .collect();
+        index = end;
+
+        let group_width: f32 = group
+            .iter()
+            .map(|(text, style)| text_width(text, style.face(), size))
+            .sum();
+        let space_width = match group.first() {
+            Some((_, style)) if space && !line.runs.is_empty() => {
+                text_width(" ", style.face(), size)
+            }
+            _ => 0.0,
+        };
+
+        if !line.runs.is_empty() && line.width + space_width + group_width > width {
+            lines.push(std::mem::take(&mut line));
+        }
+
+        if group_width > width {
+            for (text, style) in group {
+                for c in text.chars() {
+                    let mut buffer = [0; 4];
+                    let c = c.encode_utf8(&mut buffer);
+                    if !line.runs.is_empty()
+                        && line.width + text_width(c, style.face(), size) > width
+                    {
+                        lines.push(std::mem::take(&mut line));
+                    }
+                    line.place(c, style, false, size);
+                }
+            }
+            continue;
+        }
+
+        for (position, (text, style)) in group.into_iter().enumerate() {
+            line.place(text, style, position == 0 && space, size);
+        }
+    }
+
+    if !line.runs.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Widest line `tokens` would make if nothing forced a wrap.
+fn natural_width(tokens: &[Token], size: f32) -> f32 {
+    wrap(tokens, f32::INFINITY, size)
+        .iter()
+        .map(|line| line.width)
+        .fold(0.0, f32::max)
+}
+
+/// Splits a highlighted code line into rows of at most `columns`
+/// characters.
+fn wrap_code(line: Vec<(Ink, String)>, columns: usize) -> Vec<Vec<(Ink, String)>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut used = 0;
+    for (ink, text) in line {
+        let chars: Vec<char> = text.chars().collect();
+        let mut rest = &chars[..];
+        while !rest.is_empty() {
+            if used == columns {
+                rows.push(std::mem::take(&mut row));
+                used = 0;
+            }
+            let take = (columns - used).min(rest.len());
+            row.push((ink, rest[..take].iter().collect()));
+            used += take;
+            rest = &rest[take..];
+        }
+    }
+    rows.push(row);
+    rows
+}
+
+struct Fonts {
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    italic: IndirectFontRef,
+    bold_italic: IndirectFontRef,
+    mono: IndirectFontRef,
+}
+
+impl Fonts {
+    fn load(doc: &PdfDocumentReference) -> Result<Self, String> {
+        let font = |font| doc.add_builtin_font(font).map_err(|e| e.to_string());
+        Ok(Fonts {
+            regular: font(BuiltinFont::Helvetica)?,
+            bold: font(BuiltinFont::HelveticaBold)?,
+            italic: font(BuiltinFont::HelveticaOblique)?,
+            bold_italic: font(BuiltinFont::HelveticaBoldOblique)?,
+            mono: font(BuiltinFont::Courier)?,
+        })
+    }
+
+    fn get(&self, face: Face) -> &IndirectFontRef {
+        match face {
+            Face::Regular => &self.regular,
+            Face::Bold => &self.bold,
+            Face::Italic => &self.italic,
+            Face::BoldItalic => &self.bold_italic,
+            Face::Mono => &self.mono,
+        }
+    }
+}
+
+/// Horizontal placement of a block: `left` is its indent from the margin,
+/// `tight` whether it sits in a tight list (no gaps between paragraphs).
+#[derive(Debug, Clone, Copy, Default)]
+struct Frame {
+    left: f32,
+    tight: bool,
+}
+
+impl Frame {
+    fn x(self) -> f32 {
+        MARGIN + self.left
+    }
+
+    fn width(self) -> f32 {
+        CONTENT_WIDTH - self.left
+    }
+
+    fn indent(self, by: f32) -> Self {
+        Frame {
+            left: self.left + by,
+            ..self
+        }
+    }
+}
+
+/// A table row with its cells already broken into lines.
+struct TableRow {
+    header: bool,
+    cells: Vec<Vec<Line>>,
+    height: f32,
+}
+
+struct Writer<'d> {
+    doc: PdfDocumentReference,
+    fonts: Fonts,
+    layers: Vec<PdfLayerReference>,
+    /// Distance of the next block from the top of the current page.
+    y: f32,
+    bookmarks: Vec<Bookmark>,
+    highlighter: Highlighter,
+    document: &'d Path,
+    /// Review note numbers by the source line of the block they're on.
+    markers: HashMap<usize, Vec<usize>>,
+    /// Note numbers waiting for the first line of their block to be drawn.
+    pending: Vec<usize>,
+    /// Characters drawn as `?`, since the standard fonts can't show them.
+    replaced: RefCell<BTreeSet<char>>,
+}
+
+impl<'d> Writer<'d> {
+    fn new(title: &str, document: &'d Path, notes: &[ReviewNote]) -> Result<Self, String> {
+        let (doc, page, layer) =
+            PdfDocument::new(title, mm(PAGE_WIDTH), mm(PAGE_HEIGHT), "Content");
+        let fonts = Fonts::load(&doc)?;
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(Writer {
+            doc,
+            fonts,
+            layers: vec![layer],
+            y: MARGIN,
+            bookmarks: Vec::new(),
+            // Paper is white whatever the app theme is.
+            highlighter: Highlighter::new(CodeTheme::Light),
+            document,
+            markers: notes
+                .iter()
+                .enumerate()
+                .filter_map(|(index, note)| Some((note.line?, index + 1)))
+                .fold(HashMap::new(), |mut markers, (line, number)| {
+                    markers.entry(line).or_insert_with(Vec::new).push(number);
+                    markers
+                }),
+            pending: Vec::new(),
+            replaced: RefCell::new(BTreeSet::new()),
+        })
+    }
+
+    fn layer(&self) -> &PdfLayerReference {
+        &self.layers[self.layers.len() - 1]
+    }
+
+    fn page(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    fn new_page(&mut self) {
+        let (page, layer) = self
+            .doc
+            .add_page(mm(PAGE_WIDTH), mm(PAGE_HEIGHT), "Content");
+        self.layers.push(self.doc.get_page(page).get_layer(layer));
+        self.y = MARGIN;
+    }
+
+    /// Starts a new page unless `height` still fits on this one. A block
+    /// taller than a whole page is started where it is.
+    fn ensure(&mut self, height: f32) {
+        if self.y > MARGIN && self.y + height > PAGE_HEIGHT - MARGIN {
+            self.new_page();
+        }
+    }
+
+    fn fill_rect(
+        &self,
+        layer: &PdfLayerReference,
+        x: f32,
+        top: f32,
+        width: f32,
+        height: f32,
+        ink: Ink,
+    ) {
+        layer.set_fill_color(color(ink));
+        layer.add_rect(Rect::new(
+            mm(x),
+            mm(PAGE_HEIGHT - top - height),
+            mm(x + width),
+            mm(PAGE_HEIGHT - top),
+        ));
+    }
+
+    fn stroke_rect(&self, x: f32, top: f32, width: f32, height: f32, ink: Ink) {
+        let layer = self.layer();
+        layer.set_outline_color(color(ink));
+        layer.set_outline_thickness(0.6);
+        layer.add_rect(
+            Rect::new(
+                mm(x),
+                mm(PAGE_HEIGHT - top - height),
+                mm(x + width),
+                mm(PAGE_HEIGHT - top),
+            )
+            .with_mode(PaintMode::Stroke),
+        );
+    }
+
+    fn stroke_path(&self, points: &[(f32, f32)], thickness: f32, ink: Ink) {
+        let layer = self.layer();
+        layer.set_outline_color(color(ink));
+        layer.set_outline_thickness(thickness);
+        layer.add_line(PathLine {
+            points: points
+                .iter()
+                .map(|&(x, y)| (Point::new(mm(x), mm(PAGE_HEIGHT - y)), false))
+                .collect(),
+            is_closed: false,
+        });
+    }
+
+    /// Sets `text` with its baseline starting at `(x, baseline)`.
+    fn text(
+        &self,
+        layer: &PdfLayerReference,
+        text: &str,
+        (x, baseline): (f32, f32),
+        face: Face,
+        size: f32,
+        ink: Ink,
+    ) {
+        layer.set_fill_color(color(ink));
+        layer.use_text(
+            win_ansi(text, &mut self.replaced.borrow_mut()),
+            size,
+            mm(x),
+            mm(PAGE_HEIGHT - baseline),
+            self.fonts.get(face),
+        );
+    }
+
+    /// Numbers of the review notes on the block being laid out, in the
+    /// right margin level with its first line.
+    fn margin_marker(&mut self, baseline: f32) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let label = std::mem::take(&mut self.pending)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let x = PAGE_WIDTH - MARGIN + 8.0;
+        let width = text_width(&label, Face::Bold, MARKER_SIZE) + 6.0;
+        let layer = self.layer();
+        self.fill_rect(
+            layer,
+            x,
+            baseline - MARKER_SIZE - 1.0,
+            width,
+            MARKER_SIZE + 4.0,
+            LINK,
+        );
+        self.text(
+            layer,
+            &label,
+            (x + 3.0, baseline),
+            Face::Bold,
+            MARKER_SIZE,
+            WHITE,
+        );
+    }
+
+    fn draw_line(&self, line: &Line, x: f32, baseline: f32, size: f32, ink: Ink) {
+        let layer = self.layer();
+        for run in &line.runs {
+            let left = x + run.x;
+            if run.style.code {
+                self.fill_rect(
+                    layer,
+                    left - 1.5,
+                    baseline - size,
+                    run.width + 3.0,
+                    size * 1.3,
+                    CODE_BACKGROUND,
+                );
+            }
+            let ink = if run.style.link { LINK } else { ink };
+            self.text(
+                layer,
+                &run.text,
+                (left, baseline),
+                run.style.face(),
+                size,
+                ink,
+            );
+            if run.style.strike {
+                let y = baseline - size * 0.3;
+                self.stroke_path(&[(left, y), (left + run.width, y)], 0.6, ink);
+            }
+        }
+    }
+
+    /// Lays out text and images in reading order. Images get their own
+    /// lines, between the text before and after them.
+    fn flow(&mut self, tokens: Vec<Token>, size: f32, ink: Ink, frame: Frame) {
+        let mut text = Vec::new();
+        for token in tokens {
+            match token {
+                Token::Image { url, alt } => {
+                    self.lines(&std::mem::take(&mut text), size, ink, frame);
+                    self.image(&url, &alt, frame);
+                }
+                token => text.push(token),
+            }
+        }
+        self.lines(&text, size, ink, frame);
+    }
+
+    fn lines(&mut self, tokens: &[Token], size: f32, ink: Ink, frame: Frame) {
+        let height = size * LINE_SPACING;
+        for line in wrap(tokens, frame.width(), size) {
+            self.ensure(height);
+            self.margin_marker(self.y + size);
+            self.draw_line(&line, frame.x(), self.y + size, size, ink);
+            self.y += height;
+        }
+    }
+
+    fn blocks<'a>(&mut self, parent: &'a AstNode<'a>, frame: Frame) {
+        for child in parent.children() {
+            self.block(child, frame);
+        }
+    }
+
+    fn block<'a>(&mut self, node: &'a AstNode<'a>, frame: Frame) {
+        let (value, line) = {
+            let ast = node.data.borrow();
+            (ast.value.clone(), ast.sourcepos.start.line)
+        };
+        // The outermost block on a line takes the markers; whatever it
+        // draws first places them.
+        if let Some(numbers) = self.markers.remove(&line) {
+            self.pending.extend(numbers);
+        }
+
+        match value {
+            NodeValue::Paragraph => {
+                self.flow(inlines(node, Style::default()), BODY_SIZE, TEXT, frame);
+                if !frame.tight {
+                    self.y += BLOCK_GAP;
+                }
+            }
+            NodeValue::Heading(heading) => self.heading(node, heading.level, frame),
+            // Diagram fences come out as their source: the diagram renderers
+            // produce SVG, which the standard PDF drawing model can't take.
+            NodeValue::CodeBlock(block) => self.code_block(&block.literal, &block.info, frame),
+            NodeValue::List(list) => self.list(node, &list, frame),
+            NodeValue::BlockQuote | NodeValue::MultilineBlockQuote(_) => {
+                self.quote(node, None, BORDER, frame)
+            }
+            NodeValue::Alert(alert) => {
+                let title = alert
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| alert.alert_type.default_title());
+                self.quote(node, Some(title), alert_ink(alert.alert_type), frame)
+            }
+            NodeValue::ThematicBreak => {
+                self.ensure(BLOCK_GAP * 2.0);
+                self.margin_marker(self.y + BLOCK_GAP + 3.0);
+                let layer = self.layer();
+                self.fill_rect(
+                    layer,
+                    frame.x(),
+                    self.y + BLOCK_GAP,
+                    frame.width(),
+                    1.0,
+                    BORDER,
+                );
+                self.y += BLOCK_GAP * 2.0 + 1.0;
+            }
+            NodeValue::Table(table) => self.table(node, &table.alignments, frame),
+            NodeValue::FootnoteDefinition(footnote) => self.footnote(node, &footnote.name, frame),
+            NodeValue::HtmlBlock(_) | NodeValue::FrontMatter(_) | NodeValue::Raw(_) => {}
+            _ => self.blocks(node, frame),
+        }
+    }
+
+    fn heading<'a>(&mut self, node: &'a AstNode<'a>, level: u8, frame: Frame) {
+        let tokens = inlines(
+            node,
+            Style {
+                bold: true,
+                ..Style::default()
+            },
+        );
+        self.heading_tokens(&tokens, level, frame);
+    }
+
+    fn heading_tokens(&mut self, tokens: &[Token], level: u8, frame: Frame) {
+        let size = HEADING_SIZES[usize::from(level.clamp(1, 6)) - 1];
+        let lines = wrap(tokens, frame.width(), size);
+        let line_height = size * 1.25;
+
+        if self.y > MARGIN {
+            self.y += size * 0.6;
+        }
+        // A heading is kept together with the start of its section.
+        self.ensure(lines.len() as f32 * line_height + BODY_SIZE * LINE_SPACING * 2.0);
+
+        let title = plain_text(tokens);
+        if !title.is_empty() {
+            self.bookmarks.push(Bookmark {
+                title,
+                level,
+                page: self.page(),
+                top: PAGE_HEIGHT - self.y + size * 0.4,
+            });
+        }
+
+        for line in &lines {
+            self.margin_marker(self.y + size);
+            self.draw_line(line, frame.x(), self.y + size, size, TEXT);
+            self.y += line_height;
+        }
+        if level <= 2 {
+            self.y += 3.0;
+            let layer = self.layer();
+            self.fill_rect(layer, frame.x(), self.y, frame.width(), 0.75, BORDER);
+            self.y += 0.75;
+        }
+        self.y += BLOCK_GAP;
+    }
+
+    fn code_block(&mut self, literal: &str, info: &str, frame: Frame) {
+        let literal = literal.replace('\t', "    ");
+        let lang = info.split_whitespace().next();
+        let columns = (((frame.width() - 2.0 * CODE_PADDING)
+            / text_width(" ", Face::Mono, CODE_SIZE)) as usize)
+            .max(1);
+        let rows: Vec<Vec<(Ink, String)>> = self
+            .highlighter
+            .styled_lines(&literal, lang)
+            .into_iter()
+            .flat_map(|line| {
+                let line = line
+                    .into_iter()
+                    .map(|(c, text)| {
+                        (
+                            (
+                                f32::from(c.r) / 255.0,
+                                f32::from(c.g) / 255.0,
+                                f32::from(c.b) / 255.0,
+                            ),
+                            text,
+                        )
+                    })
+                    .collect();
+                wrap_code(line, columns)
+            })
+            .collect();
+
+        let line_height = CODE_SIZE * CODE_LINE_SPACING;
+        let (x, width) = (frame.x(), frame.width());
+        self.ensure(CODE_PADDING + line_height);
+        self.fill_rect(
+            self.layer(),
+            x,
+            self.y,
+            width,
+            CODE_PADDING,
+            CODE_BACKGROUND,
+        );
+        self.y += CODE_PADDING;
+
+        for row in rows {
+            self.ensure(line_height);
+            self.margin_marker(self.y + CODE_SIZE * 1.1);
+            let layer = self.layer();
+            self.fill_rect(layer, x, self.y, width, line_height, CODE_BACKGROUND);
+            let mut column = x + CODE_PADDING;
+            for (ink, text) in row {
+                self.text(
+                    layer,
+                    &text,
+                    (column, self.y + CODE_SIZE * 1.1),
+                    Face::Mono,
+                    CODE_SIZE,
+                    ink,
+                );
+                column += text_width(&text, Face::Mono, CODE_SIZE);
+            }
+            self.y += line_height;
+        }
+
+        self.fill_rect(
+            self.layer(),
+            x,
+            self.y,
+            width,
+            CODE_PADDING,
+            CODE_BACKGROUND,
+        );
+        self.y += CODE_PADDING + BLOCK_GAP;
+    }
+
+    fn list<'a>(&mut self, node: &'a AstNode<'a>, list: &NodeList, frame: Frame) {
+        let inner = Frame {
+            left: frame.left + LIST_INDENT,
+            tight: list.tight,
+        };
+        let line_height = BODY_SIZE * LINE_SPACING;
+
+        for (index, item) in node.children().enumerate() {
+            let task = match &item.data.borrow().value {
+                NodeValue::TaskItem(checked) => Some(checked.is_some()),
+                _ => None,
+            };
+
+            self.ensure(line_height);
+            let baseline = self.y + BODY_SIZE;
+            self.margin_marker(baseline);
+            let layer = self.layer();
+            match (task, list.list_type) {
+                (Some(done), _) => self.checkbox(frame.x() + 3.0, baseline, done),
+                (None, ListType::Bullet) => self.text(
+                    layer,
+                    "•",
+                    (frame.x() + 5.0, baseline),
+                    Face::Regular,
+                    BODY_SIZE,
+                    TEXT,
+                ),
+                (None, ListType::Ordered) => {
+                    let delimiter = match list.delimiter {
+                        ListDelimType::Period => '.',
+                        ListDelimType::Paren => ')',
+                    };
+                    let number = format!("{}{}", list.start + index, delimiter);
+                    let width = text_width(&number, Face::Regular, BODY_SIZE);
+                    let x = inner.x() - 4.0 - width;
+                    self.text(
+                        layer,
+                        &number,
+                        (x, baseline),
+                        Face::Regular,
+                        BODY_SIZE,
+                        TEXT,
+                    );
+                }
+            }
+
+            if item.children().next().is_none() {
+                self.y += line_height;
+            }
+            self.blocks(item, inner);
+        }
+
+        if list.tight && !frame.tight {
+            self.y += BLOCK_GAP;
+        }
+    }
+
+    fn checkbox(&self, x: f32, baseline: f32, done: bool) {
+        let side = BODY_SIZE * 0.75;
+        let top = baseline - side;
+        if done {
+            self.fill_rect(self.layer(), x, top, side, side, LINK);
+            self.stroke_path(
+                &[
+                    (x + side * 0.2, top + side * 0.5),
+                    (x + side * 0.42, top + side * 0.75),
+                    (x + side * 0.8, top + side * 0.25),
+                ],
+                1.2,
+                WHITE,
+            );
+        } else {
+            self.stroke_rect(x, top, side, side, MUTED);
+        }
+    }
+
+    /// Block quotes and alerts: indented content with a bar down the left,
+    /// drawn on every page the quote runs across.
+    fn quote<'a>(&mut self, node: &'a AstNode<'a>, title: Option<String>, bar: Ink, frame: Frame) {
+        let inner = Frame {
+            left: frame.left + QUOTE_INDENT,
+            tight: false,
+        };
+        let (first_page, start) = (self.page(), self.y);
+
+        if let Some(title) = title {
+            let mut tokens = Inlines::default();
+            tokens.text(
+                &title,
+                Style {
+                    bold: true,
+                    ..Style::default()
+                },
+            );
+            self.lines(&tokens.tokens, BODY_SIZE, bar, inner);
+            self.y += BLOCK_GAP / 2.0;
+        }
+        self.blocks(node, inner);
+
+        // The last block left a gap below itself; the bar stops short of it.
+        let end = (self.y - BLOCK_GAP).max(if self.page() == first_page {
+            start
+        } else {
+            MARGIN
+        });
+        for page in first_page..=self.page() {
+            let top = if page == first_page { start } else { MARGIN };
+            let bottom = if page == self.page() {
+                end
+            } else {
+                PAGE_HEIGHT - MARGIN
+            };
+            self.fill_rect(
+                &self.layers[page],
+                frame.x() + 2.0,
+                top,
+                3.0,
+                bottom - top,
+                bar,
+            );
+        }
+    }
+
+    fn footnote<'a>(&mut self, node: &'a AstNode<'a>, name: &str, frame: Frame) {
+        let inner = frame.indent(LIST_INDENT);
+        self.ensure(BODY_SIZE * LINE_SPACING);
+        self.margin_marker(self.y + BODY_SIZE);
+        let label = format!("[{}]", name);
+        self.text(
+            self.layer(),
+            &label,
+            (frame.x(), self.y + BODY_SIZE),
+            Face::Regular,
+            BODY_SIZE,
+            MUTED,
+        );
+        self.blocks(node, inner);
+    }
+
+    fn image(&mut self, url: &str, alt: &str, frame: Frame) {
+        let picture = links::resolve(url, Some(self.document))
+            .and_then(|link| link.path)
+            .and_then(|path| image_crate::open(path).ok());
+
+        // Remote images and formats the image crate can't decode (SVG) are
+        // named instead of dropped.
+        let Some(picture) = picture else {
+            let mut tokens = Inlines::default();
+            let label = if alt.is_empty() { url } else { alt };
+            tokens.text(
+                &format!("[image: {}]", label),
+                Style {
+                    italic: true,
+                    ..Style::default()
+                },
+            );
+            self.lines(&tokens.tokens, BODY_SIZE, MUTED, frame);
+            return;
+        };
+
+        let (pixels_wide, pixels_high) = (picture.width() as f32, picture.height() as f32);
+        if pixels_wide == 0.0 || pixels_high == 0.0 {
+            return;
+        }
+        // Images are sized like a browser would show them, 96 pixels to the
+        // inch, and shrunk to fit the column and the page.
+        let scale = (0.75_f32)
+            .min(frame.width() / pixels_wide)
+            .min(CONTENT_HEIGHT * 0.9 / pixels_high);
+        let height = pixels_high * scale;
+
+        self.ensure(height);
+        self.margin_marker(self.y + BODY_SIZE);
+        Image::from_dynamic_image(&picture).add_to_layer(
+            self.layer().clone(),
+            ImageTransform {
+                translate_x: Some(mm(frame.x())),
+                translate_y: Some(mm(PAGE_HEIGHT - self.y - height)),
+                scale_x: Some(scale),
+                scale_y: Some(scale),
+                // At 72 dpi one pixel is one point, so `scale` maps straight
+                // to the size on paper.
+                dpi: Some(72.0),
+                ..Default::default()
+            },
+        );
+        self.y += height + BLOCK_GAP / 2.0;
+    }
+
+    fn table<'a>(&mut self, node: &'a AstNode<'a>, alignments: &[TableAlignment], frame: Frame) {
+        let cells: Vec<(bool, Vec<Vec<Token>>)> = node
+            .children()
+            .map(|row| {
+                let header = matches!(row.data.borrow().value, NodeValue::TableRow(true));
+                let style = Style {
+                    bold: header,
+                    ..Style::default()
+                };
+                (
+                    header,
+                    row.children().map(|cell| inlines(cell, style)).collect(),
+                )
+            })
+            .collect();
+
+        let columns = cells
+            .iter()
+            .map(|(_, row)| row.len())
+            .max()
+            .unwrap_or_default()
+            .max(alignments.len());
+        if columns == 0 {
+            return;
+        }
+
+        let mut natural = vec![0.0_f32; columns];
+        for (_, row) in &cells {
+            for (column, tokens) in row.iter().enumerate() {
+                natural[column] =
+                    natural[column].max(natural_width(tokens, TABLE_SIZE) + 2.0 * CELL_PADDING);
+            }
+        }
+        // Columns keep their natural width when the table fits and share
+        // the column width in proportion to it when it doesn't.
+        let total: f32 = natural.iter().sum();
+        let widths: Vec<f32> = if total <= frame.width() {
+            natural
+        } else {
+            natural
+                .iter()
+                .map(|width| width / total * frame.width())
+                .collect()
+        };
+
+        let line_height = TABLE_SIZE * LINE_SPACING;
+        let rows: Vec<TableRow> = cells
+            .into_iter()
+            .map(|(header, row)| {
+                let cells: Vec<Vec<Line>> = widths
+                    .iter()
+                    .enumerate()
+                    .map(|(column, width)| {
+                        row.get(column)
+                            .map(|tokens| wrap(tokens, width - 2.0 * CELL_PADDING, TABLE_SIZE))
+                            .unwrap_or_default()
+                    })
+                    .collect();
+                let lines = cells.iter().map(Vec::len).max().unwrap_or_default().max(1);
+                TableRow {
+                    header,
+                    cells,
+                    height: lines as f32 * line_height + 2.0 * CELL_PADDING,
+                }
+            })
+            .collect();
+
+        // A table that spans pages repeats its header row on each of them.
+        let header = rows.iter().position(|row| row.header);
+        for (index, row) in rows.iter().enumerate() {
+            if self.y > MARGIN && self.y + row.height > PAGE_HEIGHT - MARGIN {
+                self.new_page();
+                if let Some(header) = header.filter(|&header| header != index) {
+                    self.table_row(&rows[header], &widths, alignments, frame);
+                }
+            }
+            self.table_row(row, &widths, alignments, frame);
+        }
+        self.y += BLOCK_GAP;
+    }
+
+    fn table_row(
+        &mut self,
+        row: &TableRow,
+        widths: &[f32],
+        alignments: &[TableAlignment],
+        frame: Frame,
+    ) {
+        self.margin_marker(self.y + CELL_PADDING + TABLE_SIZE);
+        let layer = self.layer();
+        let mut x = frame.x();
+        for (column, (lines, width)) in row.cells.iter().zip(widths).enumerate() {
+            if row.header {
+                self.fill_rect(layer, x, self.y, *width, row.height, HEADER_BACKGROUND);
+            }
+            self.stroke_rect(x, self.y, *width, row.height, BORDER);
+
+            let inner = width - 2.0 * CELL_PADDING;
+            let mut baseline = self.y + CELL_PADDING + TABLE_SIZE;
+            for line in lines {
+                let offset = match alignments.get(column) {
+                    Some(TableAlignment::Center) => (inner - line.width) / 2.0,
+                    Some(TableAlignment::Right) => inner - line.width,
+                    _ => 0.0,
+                };
+                self.draw_line(
+                    line,
+                    x + CELL_PADDING + offset.max(0.0),
+                    baseline,
+                    TABLE_SIZE,
+                    TEXT,
+                );
+                baseline += TABLE_SIZE * LINE_SPACING;
+            }
+            x += width;
+        }
+        self.y += row.height;
+    }
+
+    /// The review notes, numbered like their margin markers, after the
+    /// document.
+    fn endnotes(&mut self, notes: &[ReviewNote], blocks: &[SourceBlock]) {
+        let mut heading = Inlines::default();
+        heading.text(
+            "Review comments",
+            Style {
+                bold: true,
+                ..Style::default()
+            },
+        );
+        self.heading_tokens(&heading.tokens, 1, Frame::default());
+
+        for (index, note) in notes.iter().enumerate() {
+            let mut meta = format!(
+                "{}. {} · {}",
+                index + 1,
+                note.status(),
+                review::format_timestamp(note.timestamp)
+            );
+            if let Some(line) = note.line {
+                meta.push_str(" · ");
+                meta.push_str(&review::describe_lines(review::block_lines(blocks, line)));
+            }
+            let mut tokens = Inlines::default();
+            tokens.text(
+                &meta,
+                Style {
+                    bold: true,
+                    ..Style::default()
+                },
+            );
+            let ink = if note.resolved { RESOLVED } else { OPEN };
+            self.ensure(BODY_SIZE * LINE_SPACING * 2.0);
+            self.lines(&tokens.tokens, BODY_SIZE, ink, Frame::default());
+
+            let mut text = Inlines::default();
+            for (position, line) in note.text.trim().lines().enumerate() {
+                if position > 0 {
+                    text.push(Token::Break);
+                }
+                text.text(line, Style::default());
+            }
+            let ink = if note.resolved { MUTED } else { TEXT };
+            self.lines(&text.tokens, BODY_SIZE, ink, Frame::default());
+            self.y += BLOCK_GAP;
+        }
+    }
+
+    fn page_numbers(&self) {
+        let total = self.layers.len();
+        for (index, layer) in self.layers.iter().enumerate() {
+            let label = format!("{} / {}", index + 1, total);
+            let width = text_width(&label, Face::Regular, FOOTER_SIZE);
+            let baseline = PAGE_HEIGHT - MARGIN / 2.0;
+            self.text(
+                layer,
+                &label,
+                ((PAGE_WIDTH - width) / 2.0, baseline),
+                Face::Regular,
+                FOOTER_SIZE,
+                MUTED,
+            );
+        }
+    }
+
+    fn finish(self) -> Result<Pdf, String> {
+        self.page_numbers();
+        let pdf = self.doc.save_to_bytes().map_err(|e| e.to_string())?;
+        Ok(Pdf {
+            bytes: bookmarks::add_outline(pdf, &self.bookmarks)?,
+            replaced: self.replaced.into_inner().into_iter().collect(),
+        })
+    }
+}
+
+fn alert_ink(alert_type: AlertType) -> Ink {
+    match alert_type {
+        AlertType::Note => (0.04, 0.41, 0.85),
+        AlertType::Tip => (0.1, 0.5, 0.22),
+        AlertType::Important => (0.51, 0.31, 0.87),
+        AlertType::Warning => (0.6, 0.4, 0.0),
+        AlertType::Caution => (0.82, 0.14, 0.18),
+    }
+}
+
+/// An exported document.
+pub struct Pdf {
+    pub bytes: Vec<u8>,
+    /// Characters the standard fonts have no glyph for, drawn as `?`.
+    pub replaced: Vec<char>,
+}
+
+impl Pdf {
+    /// Tells which characters couldn't be shown, if any did.
+    pub fn warning(&self) -> Option<String> {
+        if self.replaced.is_empty() {
+            return None;
+        }
+        let characters: Vec<String> = self.replaced.iter().map(char::to_string).collect();
+        Some(format!(
+            "The PDF fonts can't show these characters, so they appear as '?': {}",
+            characters.join(" ")
+        ))
+    }
+}
+
+/// Lays out a document as an A4 PDF with the headings as nested bookmarks.
+/// Local images are embedded; remote ones are named by their alt text.
+/// Review `notes`, in [`review::sort`] order, are numbered in the margin
+/// and listed at the end.
+pub fn export(
+    content: &str,
+    profile: &RenderProfile,
+    document: &Path,
+    notes: &[ReviewNote],
+) -> Result<Pdf, String> {
+    let arena = Arena::new();
+    let root = parse(&arena, content, profile);
+
+    let title = headings::extract(root)
+        .into_iter()
+        .next()
+        .map(|heading| heading.text)
+        .or_else(|| {
+            document
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_default();
+
+    let mut writer = Writer::new(&title, document, notes)?;
+    writer.blocks(root, Frame::default());
+    if !notes.is_empty() {
+        writer.endnotes(notes, &sourcepos::collect(root));
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_characters_the_fonts_cannot_show() {
+        let pdf = export(
+            "# Título\n\nCafé → 東京 ✓ “quoted” →\n",
+            &RenderProfile::default(),
+            Path::new("notes.md"),
+            &[],
+        )
+        .unwrap();
+        assert!(pdf.bytes.starts_with(b"%PDF"));
+        assert_eq!(pdf.replaced, vec!['→', '✓', '京', '東']);
+        assert_eq!(
+            pdf.warning().unwrap(),
+            "The PDF fonts can't show these characters, so they appear as '?': → ✓ 京 東"
+        );
+    }
+
+    #[test]
+    fn no_warning_for_win_ansi_text() {
+        let pdf = export(
+            "Café – “naïve” €5\n",
+            &RenderProfile::default(),
+            Path::new("a.md"),
+            &[],
+        )
+        .unwrap();
+        assert!(pdf.warning().is_none());
+    }
+}
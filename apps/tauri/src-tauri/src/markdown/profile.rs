@@ -0,0 +1,139 @@
+use comrak::Options;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Named starting points for the render profile. Picking one overwrites
+/// every extension flag; editing a flag afterwards turns the profile into
+/// `"custom"`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    Github,
+    Commonmark,
+    Obsidian,
+}
+
+/// Which comrak extensions are enabled when rendering documents.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RenderProfile {
+    pub preset: String,
+    pub table: bool,
+    pub tasklist: bool,
+    pub strikethrough: bool,
+    pub autolink: bool,
+    pub footnotes: bool,
+    pub alerts: bool,
+    /// `$inline$`, `$$display$$` and ```` ```math ```` blocks.
+    pub math: bool,
+    pub superscript: bool,
+    pub subscript: bool,
+    pub header_ids: bool,
+    pub description_lists: bool,
+    /// `[[target|title]]` links, as written by Obsidian.
+    pub wikilinks: bool,
+    /// Render raw HTML instead of omitting it. The output is always run
+    /// through the allowlist sanitizer when this is on. Not part of any
+    /// preset, so switching presets keeps the current choice.
+    pub raw_html: bool,
+}
+
+impl Default for RenderProfile {
+    fn default() -> Self {
+        Preset::Github.profile()
+    }
+}
+
+impl Preset {
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Github => "github",
+            Preset::Commonmark => "commonmark",
+            Preset::Obsidian => "obsidian",
+        }
+    }
+
+    pub fn profile(self) -> RenderProfile {
+        let github = RenderProfile {
+            preset: self.name().to_string(),
+            table: true,
+            tasklist: true,
+            strikethrough: true,
+            autolink: true,
+            footnotes: true,
+            alerts: true,
+            math: true,
+            superscript: false,
+            subscript: false,
+            header_ids: true,
+            description_lists: false,
+            wikilinks: false,
+            raw_html: false,
+        };
+
+        match self {
+            Preset::Github => github,
+            Preset::Commonmark => RenderProfile {
+                preset: self.name().to_string(),
+                table: false,
+                tasklist: false,
+                strikethrough: false,
+                autolink: false,
+                footnotes: false,
+                alerts: false,
+                math: false,
+                superscript: false,
+                subscript: false,
+                header_ids: false,
+                description_lists: false,
+                wikilinks: false,
+                raw_html: false,
+            },
+            Preset::Obsidian => RenderProfile {
+                superscript: true,
+                wikilinks: true,
+                ..github
+            },
+        }
+    }
+}
+
+impl RenderProfile {
+    pub fn apply(&self, options: &mut Options) {
+        options.extension.table = self.table;
+        options.extension.tasklist = self.tasklist;
+        options.extension.strikethrough = self.strikethrough;
+        options.extension.autolink = self.autolink;
+        options.extension.footnotes = self.footnotes;
+        options.extension.alerts = self.alerts;
+        options.extension.math_dollars = self.math;
+        options.extension.math_code = self.math;
+        options.extension.superscript = self.superscript;
+        options.extension.subscript = self.subscript;
+        options.extension.header_ids = self.header_ids.then(String::new);
+        options.extension.description_lists = self.description_lists;
+        options.extension.wikilinks_title_after_pipe = self.wikilinks;
+        options.render.unsafe_ = self.raw_html;
+    }
+}
+
+pub fn profile_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join("render-profile.json")
+}
+
+pub fn load_profile(app_data_dir: &Path) -> RenderProfile {
+    let path = profile_path(app_data_dir);
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_profile(app_data_dir: &Path, profile: &RenderProfile) -> Result<(), String> {
+    let path = profile_path(app_data_dir);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(profile).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())
+}
@@ -0,0 +1,151 @@
+//! Review comments in exported documents.
+//!
+//! Exports that include comments number them in document order, mark the
+//! commented block with the number and list the comments as endnotes, so
+//! the export works as a record of the review.
+
+use comrak::Arena;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::sourcepos::{self, SourceBlock};
+use super::{parse, RenderProfile};
+
+/// Longest stretch of a commented block quoted in a report.
+const QUOTE_LINES: usize = 12;
+
+/// A review comment, reduced to what an export shows.
+#[derive(Debug, Clone)]
+pub struct ReviewNote {
+    pub text: String,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: i64,
+    pub resolved: bool,
+    /// First markdown line of the commented blocks, if known.
+    pub line: Option<usize>,
+}
+
+impl ReviewNote {
+    pub fn status(&self) -> &'static str {
+        if self.resolved {
+            "Resolved"
+        } else {
+            "Open"
+        }
+    }
+}
+
+/// Puts notes in the order they're numbered: by position in the document,
+/// then by age. Notes without a position go last.
+pub fn sort(notes: &mut [ReviewNote]) {
+    notes.sort_by_key(|note| (note.line.is_none(), note.line, note.timestamp));
+}
+
+/// `YYYY-MM-DD HH:MM UTC` for a timestamp in milliseconds.
+pub fn format_timestamp(millis: i64) -> String {
+    let seconds = millis.div_euclid(1000);
+    let (days, time) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60
+    )
+}
+
+/// Lines of the outermost block that starts on `line`.
+pub fn block_lines(blocks: &[SourceBlock], line: usize) -> (usize, usize) {
+    blocks
+        .iter()
+        .find(|block| block.start_line == line)
+        .map_or((line, line), |block| {
+            (block.start_line, block.end_line.max(line))
+        })
+}
+
+/// "line 12" or "lines 12–14".
+pub fn describe_lines((start, end): (usize, usize)) -> String {
+    if end > start {
+        format!("lines {}–{}", start, end)
+    } else {
+        format!("line {}", start)
+    }
+}
+
+/// A Markdown review record: every comment with its status, time and the
+/// source of the block it was left on, ready to paste into a pull request.
+pub fn report(content: &str, profile: &RenderProfile, notes: &[ReviewNote], title: &str) -> String {
+    let arena = Arena::new();
+    let root = parse(&arena, content, profile);
+    let blocks = sourcepos::collect(root);
+    let source: Vec<&str> = content.lines().collect();
+
+    let resolved = notes.iter().filter(|note| note.resolved).count();
+    let exported = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis() as i64)
+        .unwrap_or_default();
+
+    let mut report = format!("# Review: {}\n\n", title);
+    report.push_str(&format!(
+        "{} comments, {} open, {} resolved. Exported {}.\n",
+        notes.len(),
+        notes.len() - resolved,
+        resolved,
+        format_timestamp(exported)
+    ));
+
+    for (index, note) in notes.iter().enumerate() {
+        let lines = note.line.map(|line| block_lines(&blocks, line));
+        report.push_str(&format!("\n## {}. {}", index + 1, note.status()));
+        if let Some(lines) = lines {
+            report.push_str(&format!(" · {}", describe_lines(lines)));
+        }
+        report.push_str(&format!("\n\n_{}_\n\n", format_timestamp(note.timestamp)));
+
+        if let Some((start, end)) = lines {
+            let quoted: Vec<&str> = source
+                .iter()
+                .skip(start.saturating_sub(1))
+                .take((end + 1 - start).min(QUOTE_LINES))
+                .copied()
+                .collect();
+            for line in &quoted {
+                if line.is_empty() {
+                    report.push_str(">\n");
+                } else {
+                    report.push_str(&format!("> {}\n", line));
+                }
+            }
+            if end + 1 - start > QUOTE_LINES {
+                report.push_str("> …\n");
+            }
+            if !quoted.is_empty() {
+                report.push('\n');
+            }
+        }
+
+        report.push_str(note.text.trim());
+        report.push('\n');
+    }
+
+    report
+}
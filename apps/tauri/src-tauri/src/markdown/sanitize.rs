@@ -0,0 +1,245 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use super::Output;
+
+/// Prepended to every id in sanitized HTML. The view looks fragments up
+/// with and without it.
+pub const ID_PREFIX: &str = "user-content-";
+
+/// Attributes the app writes itself and reads back: block and line
+/// mapping, diagram placeholders, and the paging data of embedded tables.
+/// Only rendered output keeps them, and raw HTML in a document has them
+/// renamed by [`defuse`] before it's formatted, so none can be forged.
+const INTERNAL_ATTRIBUTES: &[&str] = &[
+    "data-sourcepos",
+    "data-diagram",
+    "data-line",
+    "data-total",
+    "data-page-size",
+    "data-header",
+    "data-column",
+];
+
+/// Classes rendered output uses, by prefix. Any other class is dropped, so
+/// a document can't take on the styles of the app around it.
+const CLASS_PREFIXES: &[&str] = &[
+    "language-",
+    "markdown-alert",
+    "footnote",
+    "anchor",
+    "diagram",
+    "nb-",
+    "dt-",
+    "data-table",
+    "line",
+    "numeric",
+];
+
+/// Cleans untrusted HTML, like a notebook's HTML outputs, before a
+/// converter puts it in a document.
+///
+/// Starts from ammonia's allowlist (which already drops `<script>`,
+/// `<style>`, event handler attributes and `javascript:` URLs) and widens
+/// it just enough for content to look like rendered markdown: task list
+/// checkboxes, the classes of [`CLASS_PREFIXES`], the inline colors written
+/// by the syntax highlighter, and the `file://`, asset and (for images
+/// only) `data:` URLs that local links and images are resolved to.
+///
+/// Ids are prefixed with [`ID_PREFIX`], as GitHub does, so a document
+/// can't take over the ids of the app's own elements.
+pub fn clean(html: &str) -> String {
+    builder(false)
+        .id_prefix(Some(ID_PREFIX))
+        .clean(html)
+        .to_string()
+}
+
+/// Cleans a formatted document when raw HTML passthrough is enabled. Unlike
+/// [`clean`], this keeps [`INTERNAL_ATTRIBUTES`], which only the renderer
+/// can have written once raw HTML is [`defuse`]d. Ids in HTML that leaves
+/// the app, like an export, are kept as they are, since links to them have
+/// nothing to look them up.
+pub fn clean_rendered(html: &str, output: Output) -> String {
+    let mut builder = builder(true);
+    if output == Output::View {
+        builder.id_prefix(Some(ID_PREFIX));
+    }
+    builder.clean(html).to_string()
+}
+
+/// Renames [`INTERNAL_ATTRIBUTES`] in a document's raw HTML, so they don't
+/// survive [`clean_rendered`]. Attribute names are matched without regard
+/// to case, as browsers do.
+pub fn defuse(raw: &str) -> String {
+    let mut defused = raw.to_string();
+    for name in INTERNAL_ATTRIBUTES {
+        let mut from = 0;
+        while let Some(at) = defused[from..]
+            .to_ascii_lowercase()
+            .find(name)
+            .map(|at| from + at)
+        {
+            // `data-` becomes `data-raw-`, which nothing allows.
+            defused.insert_str(at + "data-".len(), "raw-");
+            from = at + name.len();
+        }
+    }
+    defused
+}
+
+fn builder(rendered: bool) -> ammonia::Builder<'static> {
+    let mut builder = ammonia::Builder::default();
+    if rendered {
+        builder.add_generic_attributes(INTERNAL_ATTRIBUTES);
+    }
+    builder
+        .add_tags(&["input", "section"])
+        .add_generic_attributes(&[
+            "class",
+            "id",
+            "align",
+            "dir",
+            "aria-hidden",
+            "aria-label",
+            "data-math-style",
+        ])
+        .add_tag_attributes(
+            "a",
+            &[
+                "title",
+                "data-footnote-ref",
+                "data-footnote-backref",
+                "data-footnote-backref-idx",
+            ],
+        )
+        .add_tag_attributes("section", &["data-footnotes"])
+        .add_tag_attributes("img", &["title"])
+        .add_tag_attributes("details", &["open"])
+        .add_tag_attributes("input", &["checked", "disabled"])
+        .add_tag_attribute_values("input", "type", &["checkbox"])
+        .add_tag_attributes("span", &["style"])
+        .add_url_schemes(&["file", "data", super::assets::SCHEME])
+        .attribute_filter(|element, attribute, value| {
+            if attribute == "class" {
+                let classes: Vec<&str> = value
+                    .split_ascii_whitespace()
+                    .filter(|class| {
+                        CLASS_PREFIXES
+                            .iter()
+                            .any(|prefix| class.starts_with(prefix))
+                    })
+                    .collect();
+                return (!classes.is_empty()).then(|| Cow::Owned(classes.join(" ")));
+            }
+            let is_data = value
+                .trim_start()
+                .get(..5)
+                .is_some_and(|s| s.eq_ignore_ascii_case("data:"));
+            if is_data && !(element == "img" && attribute == "src") {
+                None
+            } else {
+                Some(Cow::Borrowed(value))
+            }
+        })
+        .filter_style_properties(HashSet::from([
+            "color",
+            "font-weight",
+            "font-style",
+            "text-decoration",
+        ]));
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_scripts_and_event_handlers() {
+        let html = clean(
+            "<p onclick=\"alert(1)\">a</p><script>alert(2)</script><img src=x onerror=alert(3)>",
+        );
+        assert!(!html.contains("alert"), "{}", html);
+        assert!(!html.contains("<script"), "{}", html);
+    }
+
+    #[test]
+    fn drops_javascript_urls() {
+        let html =
+            clean("<a href=\"javascript:alert(1)\">a</a><a href=\" JaVaScRiPt:alert(1)\">b</a>");
+        assert!(
+            !html.to_ascii_lowercase().contains("javascript"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn allows_data_urls_only_as_image_sources() {
+        let html = clean(
+            "<img src=\"data:image/png;base64,AAAA\"><a href=\"data:text/html,<script>alert(1)</script>\">a</a>",
+        );
+        assert!(
+            html.contains("src=\"data:image/png;base64,AAAA\""),
+            "{}",
+            html
+        );
+        assert!(!html.contains("data:text/html"), "{}", html);
+    }
+
+    #[test]
+    fn prefixes_ids_in_the_view_only() {
+        assert_eq!(
+            clean("<p id=\"content\">a</p>"),
+            "<p id=\"user-content-content\">a</p>"
+        );
+        assert_eq!(
+            clean_rendered("<p id=\"fn-1\">a</p>", Output::Standalone),
+            "<p id=\"fn-1\">a</p>"
+        );
+    }
+
+    #[test]
+    fn keeps_only_rendering_classes() {
+        assert_eq!(
+            clean("<div class=\"modal markdown-alert task-checkbox\">a</div>"),
+            "<div class=\"markdown-alert\">a</div>"
+        );
+        assert_eq!(clean("<div class=\"modal\">a</div>"), "<div>a</div>");
+    }
+
+    #[test]
+    fn strips_internal_attributes_from_untrusted_html() {
+        let html =
+            clean("<div data-diagram=\"0\" data-sourcepos=\"1:1-1:1\" data-line=\"2\"></div>");
+        assert_eq!(html, "<div></div>");
+    }
+
+    #[test]
+    fn defused_raw_html_cannot_forge_internal_attributes() {
+        let raw = defuse(
+            "<DIV Data-Diagram=\"0\"></DIV><p data-sourcepos=\"9:1-9:1\" data-SOURCEPOS=x>a</p>",
+        );
+        let html = clean_rendered(
+            &format!("{}<p data-sourcepos=\"1:1-1:5\">b</p>", raw),
+            Output::View,
+        );
+        assert_eq!(
+            html,
+            "<div></div><p>a</p><p data-sourcepos=\"1:1-1:5\">b</p>"
+        );
+    }
+
+    #[test]
+    fn keeps_what_the_renderer_writes() {
+        let rendered = "<section class=\"footnotes\" data-footnotes=\"\"><ol><li id=\"fn-1\" data-sourcepos=\"3:1-3:7\"><p>n <a href=\"#fnref-1\" class=\"footnote-backref\" data-footnote-backref=\"\" data-footnote-backref-idx=\"1\" aria-label=\"Back to reference 1\">↩</a></p></li></ol></section>";
+        let html = clean_rendered(rendered, Output::Standalone);
+        assert_eq!(
+            html,
+            rendered.replace("\">↩", "\" rel=\"noopener noreferrer\">↩")
+        );
+        let checkbox = "<input type=\"checkbox\" checked=\"\" disabled=\"\">";
+        assert_eq!(clean_rendered(checkbox, Output::View), checkbox);
+    }
+}
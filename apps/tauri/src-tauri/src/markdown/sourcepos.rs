@@ -0,0 +1,71 @@
+use comrak::nodes::{AstNode, NodeValue};
+use serde::Serialize;
+
+/// A rendered block and the markdown lines it came from.
+///
+/// `sourcepos` is the exact `data-sourcepos` attribute comrak writes on the
+/// block's HTML element, so the frontend can match DOM nodes to entries
+/// without relying on element order.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct SourceBlock {
+    pub kind: String,
+    pub sourcepos: String,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// Stable short name for every block-level node that gets its own element.
+pub fn block_kind(value: &NodeValue) -> Option<&'static str> {
+    match value {
+        NodeValue::Heading(_) => Some("heading"),
+        NodeValue::Paragraph => Some("paragraph"),
+        NodeValue::List(_) => Some("list"),
+        NodeValue::Item(_) | NodeValue::TaskItem(_) => Some("item"),
+        NodeValue::CodeBlock(_) => Some("code"),
+        // Only produced by the diagram pass, which swaps fences for
+        // figures, and by notebook output embedding.
+        NodeValue::Raw(html) if html.starts_with("<figure") => Some("diagram"),
+        NodeValue::Raw(_) => Some("output"),
+        NodeValue::BlockQuote | NodeValue::MultilineBlockQuote(_) => Some("quote"),
+        NodeValue::Table(_) => Some("table"),
+        NodeValue::TableRow(_) => Some("table_row"),
+        NodeValue::HtmlBlock(_) => Some("html"),
+        NodeValue::ThematicBreak => Some("rule"),
+        NodeValue::FootnoteDefinition(_) => Some("footnote"),
+        NodeValue::DescriptionList => Some("description_list"),
+        NodeValue::DescriptionItem(_) => Some("description_item"),
+        NodeValue::DescriptionTerm => Some("description_term"),
+        NodeValue::DescriptionDetails => Some("description_details"),
+        NodeValue::Alert(_) => Some("alert"),
+        _ => None,
+    }
+}
+
+/// Walks the document in order and returns every block with a known
+/// source position.
+pub fn collect<'a>(root: &'a AstNode<'a>) -> Vec<SourceBlock> {
+    root.descendants()
+        .filter_map(|node| {
+            let ast = node.data.borrow();
+            let kind = block_kind(&ast.value)?;
+            if ast.sourcepos.start.line == 0 {
+                return None;
+            }
+            let start = ast.sourcepos.start;
+            let end = ast.sourcepos.end;
+            // Blocks closed by a blank line report column 0 on the line
+            // after their last character.
+            let end_line = if end.column == 0 && end.line > start.line {
+                end.line - 1
+            } else {
+                end.line
+            };
+            Some(SourceBlock {
+                kind: kind.to_string(),
+                sourcepos: ast.sourcepos.to_string(),
+                start_line: start.line,
+                end_line,
+            })
+        })
+        .collect()
+}
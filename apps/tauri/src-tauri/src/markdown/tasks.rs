@@ -0,0 +1,114 @@
+//! Ticking task list items in the markdown source.
+//!
+//! Items are addressed by the line their `data-sourcepos` starts on, and
+//! only the `[ ]` / `[x]` marker on that line is rewritten, so the rest of
+//! the file is left byte for byte as it was.
+
+use comrak::nodes::NodeValue;
+use comrak::Arena;
+
+use super::RenderProfile;
+
+/// Flips the checkbox of the task list item starting on `line` (1-based).
+/// Returns the new content and whether the item is now checked.
+pub fn toggle(
+    content: &str,
+    line: usize,
+    profile: &RenderProfile,
+) -> Result<(String, bool), String> {
+    let arena = Arena::new();
+    let root = super::parse(&arena, content, profile);
+    let (column, checked) = root
+        .descendants()
+        .find_map(|node| {
+            let ast = node.data.borrow();
+            match ast.value {
+                NodeValue::TaskItem(symbol) if ast.sourcepos.start.line == line => {
+                    Some((ast.sourcepos.start.column, symbol.is_some()))
+                }
+                _ => None,
+            }
+        })
+        .ok_or_else(|| format!("No task list item on line {}", line))?;
+
+    let start = content
+        .split_inclusive('\n')
+        .take(line - 1)
+        .map(str::len)
+        .sum::<usize>();
+    let text = content[start..].lines().next().unwrap_or_default();
+    let marker = text
+        .get(column.saturating_sub(1)..)
+        .and_then(|item| item.find('[').map(|at| at + column.saturating_sub(1)))
+        .filter(|&at| text[at..].chars().nth(2) == Some(']'))
+        .ok_or_else(|| format!("No checkbox found on line {}", line))?;
+
+    let symbol_at = start + marker + 1;
+    let symbol_len = content[symbol_at..]
+        .chars()
+        .next()
+        .map_or(1, char::len_utf8);
+    let mut updated = String::with_capacity(content.len());
+    updated.push_str(&content[..symbol_at]);
+    updated.push(if checked { ' ' } else { 'x' });
+    updated.push_str(&content[symbol_at + symbol_len..]);
+    Ok((updated, !checked))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toggled(content: &str, line: usize) -> (String, bool) {
+        toggle(content, line, &RenderProfile::default()).unwrap()
+    }
+
+    #[test]
+    fn toggles_nested_items() {
+        let content = "- [ ] a\n  - [ ] b\n    1. [x] c\n";
+        assert_eq!(
+            toggled(content, 2),
+            ("- [ ] a\n  - [x] b\n    1. [x] c\n".to_string(), true)
+        );
+        assert_eq!(
+            toggled(content, 3),
+            ("- [ ] a\n  - [ ] b\n    1. [ ] c\n".to_string(), false)
+        );
+    }
+
+    #[test]
+    fn toggles_items_in_block_quotes() {
+        let content = "> - [x] quoted\n>   - [ ] nested [link](x)\n";
+        assert_eq!(
+            toggled(content, 2),
+            ("> - [x] quoted\n>   - [x] nested [link](x)\n".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn keeps_crlf_line_endings() {
+        let content = "Intro\r\n\r\n- [ ] a\r\n- [X] b\r\n";
+        assert_eq!(
+            toggled(content, 4),
+            ("Intro\r\n\r\n- [ ] a\r\n- [ ] b\r\n".to_string(), false)
+        );
+    }
+
+    #[test]
+    fn handles_multibyte_text() {
+        let content = "# Café ☕\n\n- [ ] 日本語 [ ] ok\n- [ ] ü\n";
+        assert_eq!(
+            toggled(content, 3),
+            ("# Café ☕\n\n- [x] 日本語 [ ] ok\n- [ ] ü\n".to_string(), true)
+        );
+        assert_eq!(
+            toggled(content, 4),
+            ("# Café ☕\n\n- [ ] 日本語 [ ] ok\n- [x] ü\n".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn refuses_lines_without_a_task() {
+        assert!(toggle("- a\n", 1, &RenderProfile::default()).is_err());
+    }
+}
@@ -0,0 +1,537 @@
+//! Folders opened as workspaces.
+//!
+//! A workspace lists the documents under a folder as a tree, skipping what
+//! `.gitignore` and hidden files leave out, and watches the folder so the
+//! tree follows documents being created, deleted and renamed while it's
+//! open. Where a recursive watch takes a watch per folder, as with inotify,
+//! only the folders the tree can show are watched: ignored ones like
+//! `node_modules` or `target` would use up the OS's watch limit on a large
+//! repository, and document watches need it too.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use tauri::{Emitter, Manager};
+
+use crate::markdown::{formats, Format};
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TreeEntry {
+    pub name: String,
+    pub path: String,
+    /// A folder's entries, folders first; `None` for documents.
+    pub children: Option<Vec<TreeEntry>>,
+}
+
+/// A change under the workspace folder, emitted as `workspace-changed`.
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Change {
+    Created { path: String },
+    Deleted { path: String },
+    Renamed { from: String, to: String },
+}
+
+/// Whether one watch covers a folder and everything below it (FSEvents,
+/// ReadDirectoryChangesW). Elsewhere folders are watched one by one.
+const WATCHES_RECURSIVELY: bool = cfg!(any(target_os = "macos", target_os = "windows"));
+
+struct Watch {
+    root: PathBuf,
+    watcher: notify::RecommendedWatcher,
+    /// Folders being watched, each without its subfolders.
+    folders: HashSet<PathBuf>,
+}
+
+impl Watch {
+    fn add(&mut self, folder: &Path) {
+        if !self.folders.contains(folder)
+            && self.watcher.watch(folder, RecursiveMode::NonRecursive).is_ok()
+        {
+            self.folders.insert(folder.to_path_buf());
+        }
+    }
+
+    /// Stops watching `path` and any folder below it.
+    fn remove(&mut self, path: &Path) {
+        let gone: Vec<PathBuf> = self
+            .folders
+            .iter()
+            .filter(|folder| folder.starts_with(path))
+            .cloned()
+            .collect();
+        for folder in gone {
+            let _ = self.watcher.unwatch(&folder);
+            self.folders.remove(&folder);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct WorkspaceState {
+    watch: Mutex<Option<Watch>>,
+}
+
+/// Files the tree lists: prose documents, not data or source files.
+fn is_document(path: &Path) -> bool {
+    matches!(
+        Format::detect(path),
+        Some(Format::Markdown | Format::Notebook | Format::Org | Format::Rst)
+    )
+}
+
+impl TreeEntry {
+    fn new(path: &Path, children: Option<Vec<TreeEntry>>) -> Self {
+        TreeEntry {
+            name: path.file_name().map_or_else(
+                || path.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            ),
+            path: path.to_string_lossy().into_owned(),
+            children,
+        }
+    }
+
+    /// Adds the document at `relative` below this folder, creating the
+    /// folders in between.
+    fn insert(&mut self, relative: &Path) {
+        let Some(children) = self.children.as_mut() else {
+            return;
+        };
+        let mut components = relative.components();
+        let Some(first) = components.next() else {
+            return;
+        };
+        let path = Path::new(&self.path).join(first);
+        let rest = components.as_path();
+        if rest.as_os_str().is_empty() {
+            children.push(TreeEntry::new(&path, None));
+            return;
+        }
+
+        let path_str = path.to_string_lossy();
+        let index = match children
+            .iter()
+            .position(|child| child.children.is_some() && child.path == path_str)
+        {
+            Some(index) => index,
+            None => {
+                children.push(TreeEntry::new(&path, Some(Vec::new())));
+                children.len() - 1
+            }
+        };
+        children[index].insert(rest);
+    }
+
+    fn sort(&mut self) {
+        if let Some(children) = self.children.as_mut() {
+            children
+                .sort_by_cached_key(|child| (child.children.is_none(), child.name.to_lowercase()));
+            children.iter_mut().for_each(TreeEntry::sort);
+        }
+    }
+}
+
+/// The documents under `root` as a tree. Folders without documents are
+/// left out. `.gitignore` files apply even outside a git repository.
+pub fn scan(root: &Path) -> Result<TreeEntry, String> {
+    let (documents, _) = walk(root)?;
+    Ok(tree(root, documents))
+}
+
+fn tree(root: &Path, mut documents: Vec<PathBuf>) -> TreeEntry {
+    documents.sort();
+    let mut tree = TreeEntry::new(root, Some(Vec::new()));
+    for document in &documents {
+        if let Ok(relative) = document.strip_prefix(root) {
+            tree.insert(relative);
+        }
+    }
+    tree.sort();
+    tree
+}
+
+/// The documents and folders under `root` that aren't hidden or ignored,
+/// `root` included among the folders.
+fn walk(root: &Path) -> Result<(Vec<PathBuf>, Vec<PathBuf>), String> {
+    if !root.is_dir() {
+        return Err(format!("Not a folder: {}", root.display()));
+    }
+    let mut documents = Vec::new();
+    let mut folders = Vec::new();
+    let entries = formats::walk(root)
+        // Unreadable entries are skipped rather than failing the whole tree.
+        .filter_map(Result::ok);
+    for entry in entries {
+        match entry.file_type() {
+            Some(t) if t.is_dir() => folders.push(entry.into_path()),
+            Some(t) if t.is_file() && is_document(entry.path()) => {
+                documents.push(entry.into_path())
+            }
+            _ => {}
+        }
+    }
+    Ok((documents, folders))
+}
+
+/// The ignore files of one folder.
+struct FolderRules {
+    ignore: Gitignore,
+    gitignore: Gitignore,
+    exclude: Gitignore,
+    repository: bool,
+}
+
+impl FolderRules {
+    fn read(folder: &Path) -> Self {
+        let file = |path: PathBuf| {
+            let mut builder = GitignoreBuilder::new(folder);
+            builder.add(path);
+            builder.build().unwrap_or_else(|_| Gitignore::empty())
+        };
+        FolderRules {
+            ignore: file(folder.join(".ignore")),
+            gitignore: file(folder.join(".gitignore")),
+            exclude: file(folder.join(".git/info/exclude")),
+            repository: folder.join(".git").exists(),
+        }
+    }
+}
+
+/// The ignore rules `scan` walks with, checked one path at a time:
+/// `.ignore` and `.gitignore` files in the path's folder and the folders
+/// above it, the repository's `.git/info/exclude` and the global gitignore.
+/// As with `ignore::WalkBuilder`, a closer file wins over one further up,
+/// `.ignore` files win over git's, and git's stop at the repository root.
+struct Rules {
+    folders: Mutex<HashMap<PathBuf, FolderRules>>,
+    global: Gitignore,
+}
+
+impl Rules {
+    fn new() -> Self {
+        Rules {
+            folders: Mutex::new(HashMap::new()),
+            global: Gitignore::global().0,
+        }
+    }
+
+    fn ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(mut folders) = self.folders.lock() else {
+            return false;
+        };
+        let above: Vec<&Path> = path.ancestors().skip(1).collect();
+        for folder in &above {
+            if !folders.contains_key(*folder) {
+                folders.insert(folder.to_path_buf(), FolderRules::read(folder));
+            }
+        }
+        let chain: Vec<&FolderRules> = above.iter().map(|folder| &folders[*folder]).collect();
+        let git_chain = match chain.iter().position(|rules| rules.repository) {
+            Some(root) => &chain[..=root],
+            None => &chain[..],
+        };
+
+        // Whether the closest of the files that has a say ignores `path`.
+        let first = |rules: &[&FolderRules], file: fn(&FolderRules) -> &Gitignore| {
+            rules
+                .iter()
+                .map(|folder| file(folder).matched(path, is_dir))
+                .find(|found| !found.is_none())
+                .map(|found| found.is_ignore())
+        };
+        first(&chain, |rules| &rules.ignore)
+            .or_else(|| first(git_chain, |rules| &rules.gitignore))
+            .or_else(|| first(git_chain, |rules| &rules.exclude))
+            .unwrap_or_else(|| self.global.matched(path, is_dir).is_ignore())
+    }
+
+    /// Drops what was read from `folder`'s ignore files, after one changed.
+    fn forget(&self, folder: &Path) {
+        if let Ok(mut folders) = self.folders.lock() {
+            folders.remove(folder);
+        }
+    }
+}
+
+/// The folders a change took away from the tree and brought into it.
+struct FolderChange {
+    gone: Option<PathBuf>,
+    added: Vec<PathBuf>,
+}
+
+/// Decides which paths under the workspace are worth an event: not hidden
+/// (`.git` churns on every commit), not ignored by the rules `scan` walks
+/// with, and either a document or one of the tree's folders.
+struct Filter {
+    root: PathBuf,
+    rules: Rules,
+    /// The folders `walk` found, kept in step with the changes since. A
+    /// deleted path can't say whether it was a folder any more; this can.
+    folders: Mutex<HashSet<PathBuf>>,
+}
+
+impl Filter {
+    fn new(root: &Path, folders: &[PathBuf]) -> Self {
+        Filter {
+            root: root.to_path_buf(),
+            rules: Rules::new(),
+            folders: Mutex::new(folders.iter().cloned().collect()),
+        }
+    }
+
+    fn was_folder(&self, path: &Path) -> bool {
+        self.folders
+            .lock()
+            .is_ok_and(|folders| folders.contains(path))
+    }
+
+    fn wants(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let hidden = relative.components().any(|component| {
+            matches!(component, Component::Normal(name) if name.to_string_lossy().starts_with('.'))
+        });
+        let exists = path.exists();
+        let is_dir = if exists {
+            path.is_dir()
+        } else {
+            self.was_folder(path)
+        };
+        // A recursive watch also reports what's below ignored folders.
+        let ignored = path
+            .ancestors()
+            .take_while(|above| *above != self.root)
+            .any(|above| {
+                if above != path {
+                    self.rules.ignored(above, true)
+                } else if exists || is_dir {
+                    self.rules.ignored(path, is_dir)
+                } else {
+                    // Gone, and not a folder the tree knew: whichever it
+                    // was, a rule for either kind leaves it out.
+                    self.rules.ignored(path, false) || self.rules.ignored(path, true)
+                }
+            });
+        !hidden && !ignored && (is_dir || is_document(path))
+    }
+
+    /// Keeps `folders` in step with a wanted change: folders created or
+    /// moved in are added, with what's below them, and folders deleted or
+    /// moved away are dropped.
+    fn follow(&self, change: &Change) -> FolderChange {
+        let (gone, added) = match change {
+            Change::Created { path } => (None, Some(path)),
+            Change::Deleted { path } => (Some(path), None),
+            Change::Renamed { from, to } => (Some(from), Some(to)),
+        };
+        let gone = gone.map(PathBuf::from);
+        let added = added
+            .map(Path::new)
+            .filter(|path| path.is_dir())
+            .and_then(|path| walk(path).ok())
+            .map_or_else(Vec::new, |(_, folders)| folders);
+        if let Ok(mut folders) = self.folders.lock() {
+            if let Some(gone) = &gone {
+                folders.retain(|folder| !folder.starts_with(gone));
+            }
+            folders.extend(added.iter().cloned());
+        }
+        FolderChange { gone, added }
+    }
+
+    fn changes(&self, event: Event) -> Vec<Change> {
+        for changed in &event.paths {
+            let name = changed.file_name().and_then(|name| name.to_str());
+            if matches!(name, Some(".gitignore" | ".ignore")) {
+                if let Some(folder) = changed.parent() {
+                    self.rules.forget(folder);
+                }
+            }
+        }
+        let path = |p: &PathBuf| p.to_string_lossy().into_owned();
+        let wanted = event.paths.iter().filter(|p| self.wants(p));
+        match event.kind {
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                wanted.map(|p| Change::Created { path: path(p) }).collect()
+            }
+            EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                wanted.map(|p| Change::Deleted { path: path(p) }).collect()
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => match &event.paths[..] {
+                [from, to] if self.wants(from) || self.wants(to) => vec![Change::Renamed {
+                    from: path(from),
+                    to: path(to),
+                }],
+                _ => Vec::new(),
+            },
+            // Backends that can't pair the two sides of a rename report
+            // each path on its own.
+            EventKind::Modify(ModifyKind::Name(_)) => wanted
+                .map(|p| {
+                    if p.exists() {
+                        Change::Created { path: path(p) }
+                    } else {
+                        Change::Deleted { path: path(p) }
+                    }
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Opens the folder at `path` as the workspace, replacing any other, and
+/// returns its tree.
+#[tauri::command]
+pub fn open_workspace(
+    path: String,
+    app: tauri::AppHandle,
+    state: tauri::State<WorkspaceState>,
+) -> Result<TreeEntry, String> {
+    let root = std::fs::canonicalize(&path).map_err(|e| format!("Failed to open folder: {}", e))?;
+    let (documents, folders) = walk(&root)?;
+    let tree = tree(&root, documents);
+
+    // Folders are added and removed from a thread of their own: the watcher
+    // waits on its event handler's thread to change a watch, so the handler
+    // can't.
+    let (changed, changes) = mpsc::channel();
+    let filter = Filter::new(&root, &folders);
+    let handler_app = app.clone();
+    let watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+        if let Ok(event) = res {
+            for change in filter.changes(event) {
+                let folders = filter.follow(&change);
+                if !WATCHES_RECURSIVELY {
+                    let _ = changed.send(folders);
+                }
+                let _ = handler_app.emit("workspace-changed", change);
+            }
+        }
+    })
+    .map_err(|e| format!("Failed to create watcher: {}", e))?;
+
+    let mut watch = Watch {
+        root: root.clone(),
+        watcher,
+        folders: HashSet::new(),
+    };
+    if WATCHES_RECURSIVELY {
+        watch
+            .watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch folder: {}", e))?;
+    } else {
+        for folder in &folders {
+            watch.add(folder);
+        }
+        if !watch.folders.contains(&root) {
+            return Err(format!("Failed to watch folder: {}", root.display()));
+        }
+        // Ends with the watcher, which owns the sending side.
+        std::thread::spawn(move || {
+            for change in changes {
+                follow_folders(&app, &root, change);
+            }
+        });
+    }
+    *state.watch.lock().map_err(|e| e.to_string())? = Some(watch);
+    Ok(tree)
+}
+
+/// Keeps the watched folders in step with the folders a change under
+/// `root` took away and brought in.
+fn follow_folders(app: &tauri::AppHandle, root: &Path, change: FolderChange) {
+    let state = app.state::<WorkspaceState>();
+    let Ok(mut guard) = state.watch.lock() else {
+        return;
+    };
+    let Some(watch) = guard.as_mut().filter(|watch| watch.root == root) else {
+        return;
+    };
+    if let Some(gone) = &change.gone {
+        watch.remove(gone);
+    }
+    for folder in &change.added {
+        watch.add(folder);
+    }
+}
+
+/// The tree of the folder at `path`, scanned again.
+#[tauri::command]
+pub fn scan_workspace(path: String) -> Result<TreeEntry, String> {
+    scan(Path::new(&path))
+}
+
+#[tauri::command]
+pub fn close_workspace(state: tauri::State<WorkspaceState>) -> Result<(), String> {
+    *state.watch.lock().map_err(|e| e.to_string())? = None;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn is_folder(path: String) -> bool {
+    Path::new(&path).is_dir()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A folder with a `.gitignore`, an ignored `target` folder and a
+    /// `docs` folder, all deleted by the time the filter sees them.
+    fn deleted_workspace(name: &str) -> (PathBuf, Filter) {
+        let root = std::env::temp_dir().join(format!("arandu-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join("docs/a.md"), "# A").unwrap();
+        let root = fs::canonicalize(&root).unwrap();
+        let (_, folders) = walk(&root).unwrap();
+        let filter = Filter::new(&root, &folders);
+        fs::remove_dir_all(root.join("target")).unwrap();
+        fs::remove_dir_all(root.join("docs")).unwrap();
+        (root, filter)
+    }
+
+    #[test]
+    fn deleted_folders_are_filtered_like_existing_ones() {
+        let (root, filter) = deleted_workspace("deleted-folders");
+        assert!(filter.wants(&root.join("docs")));
+        assert!(filter.wants(&root.join("docs/a.md")));
+        assert!(!filter.wants(&root.join("target")));
+        assert!(!filter.wants(&root.join("target/debug")));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn deleted_files_are_wanted_only_as_documents() {
+        let (root, filter) = deleted_workspace("deleted-files");
+        assert!(filter.wants(&root.join("b.md")));
+        assert!(!filter.wants(&root.join("notes.txt")));
+        assert!(!filter.wants(&root.join("build.log")));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn follows_folders_moved_away() {
+        let (root, filter) = deleted_workspace("moved-folders");
+        let docs = root.join("docs");
+        let change = filter.follow(&Change::Renamed {
+            from: docs.to_string_lossy().into_owned(),
+            to: root.join("elsewhere").to_string_lossy().into_owned(),
+        });
+        assert_eq!(change.gone, Some(docs.clone()));
+        assert!(change.added.is_empty());
+        assert!(!filter.wants(&docs));
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1 +0,0 @@
-../../../shared/highlight.min.js
\ No newline at end of file
@@ -1,6 +1,6 @@
 const { invoke } = window.__TAURI__.core;
 const { listen } = window.__TAURI__.event;
-const { open, confirm } = window.__TAURI__.dialog;
+const { open, save, confirm, message } = window.__TAURI__.dialog;
 const { getCurrentWindow } = window.__TAURI__.window;
 
 const currentWindow = getCurrentWindow();
@@ -26,6 +26,10 @@ if (navigator.userAgent.includes("Mac")) {
   if (shortcutHint) {
     shortcutHint.textContent = "Ctrl+O";
   }
+  const folderHint = document.querySelector("#btn-open-folder .shortcut-hint");
+  if (folderHint) {
+    folderHint.textContent = "Ctrl+Shift+O";
+  }
 }
 
 const THEMES = ["system", "light", "dark"];
@@ -41,38 +45,59 @@ let commentsData = { version: "1.0", file_hash: "", comments: [] };
 let selectedBlocks = [];
 let saveQueue = Promise.resolve();
 
+function tabDisplayName(path) {
+  // Display folder/filename with smart truncation
+  const parts = path.split('/');
+  if (parts.length > 1) {
+    const fileName = parts[parts.length - 1];
+    const folderName = parts[parts.length - 2];
+
+    // Truncate folder name in the middle if too long
+    const maxFolderLength = 20;
+    let displayFolder = folderName;
+    if (folderName.length > maxFolderLength) {
+      const charsToShow = maxFolderLength - 3;
+      const frontChars = Math.ceil(charsToShow / 2);
+      const backChars = Math.floor(charsToShow / 2);
+      displayFolder = folderName.substring(0, frontChars) + '...' + folderName.substring(folderName.length - backChars);
+    }
+
+    return displayFolder + '/' + fileName;
+  } else {
+    return parts[0];
+  }
+}
+
 class TabState {
   constructor(path) {
     this.id = crypto.randomUUID();
     this.path = path;
 
-    // Display folder/filename with smart truncation
-    const parts = path.split('/');
-    if (parts.length > 1) {
-      const fileName = parts[parts.length - 1];
-      const folderName = parts[parts.length - 2];
-
-      // Truncate folder name in the middle if too long
-      const maxFolderLength = 20;
-      let displayFolder = folderName;
-      if (folderName.length > maxFolderLength) {
-        const charsToShow = maxFolderLength - 3;
-        const frontChars = Math.ceil(charsToShow / 2);
-        const backChars = Math.floor(charsToShow / 2);
-        displayFolder = folderName.substring(0, frontChars) + '...' + folderName.substring(folderName.length - backChars);
-      }
-
-      this.displayName = displayFolder + '/' + fileName;
-    } else {
-      this.displayName = parts[0];
-    }
+    this.displayName = tabDisplayName(path);
 
     this.scrollPosition = 0;
 
     this.content = null;
     this.html = null;
+    this.htmlBlocks = null; // top-level blocks of the file's render, for patching it in place
+    this.frontMatter = null;
+    this.blocks = [];
+    this.links = [];
+    this.codeTheme = null;
     this.headings = null;
+    this.outline = null;
+    this.tableView = null; // page and sort of a CSV/TSV tab
+    this.lineRange = null; // lines highlighted in a source file tab
+    this.diagnostics = [];
+    this.lintError = null;
     this.hasError = false; // Track if file failed to load
+    this.fileHash = null; // hash of the file as last read or written
+    this.watch = null; // how live reload follows the file (watch_file's status)
+
+    this.editing = false;
+    this.draft = null; // editor content, saved or not
+    this.dirty = false;
+    this.diskChanged = false; // changed on disk while there were unsaved edits
 
     this.commentsData = { version: "1.0", file_hash: "", comments: [] };
     this.selectedBlocks = [];
@@ -121,30 +146,62 @@ function applyTheme(theme) {
     document.documentElement.classList.add(theme);
   }
 
-  const lightLink = document.querySelector('link[href*="highlight-light"]');
-  const darkLink = document.querySelector('link[href*="highlight-dark"]');
-  if (theme === "light") {
-    lightLink.media = "all";
-    darkLink.media = "not all";
-  } else if (theme === "dark") {
-    lightLink.media = "not all";
-    darkLink.media = "all";
-  } else {
-    lightLink.media = "(prefers-color-scheme: light)";
-    darkLink.media = "(prefers-color-scheme: dark)";
-  }
-
   const icon = document.getElementById("icon-theme");
   icon.innerHTML = THEME_ICONS[theme];
+
+  rerenderForCodeTheme();
+}
+
+const systemDarkQuery = window.matchMedia("(prefers-color-scheme: dark)");
+
+// Code blocks are highlighted by the backend, so they follow the resolved
+// light/dark theme rather than the "system" setting itself.
+function resolvedCodeTheme() {
+  if (currentTheme === "system") {
+    return systemDarkQuery.matches ? "dark" : "light";
+  }
+  return currentTheme;
+}
+
+async function rerenderForCodeTheme() {
+  const theme = resolvedCodeTheme();
+
+  for (const tab of tabs) {
+    if (tab.id === activeTabId || tab.codeTheme === theme) continue;
+    // Inactive tabs are re-rendered lazily when switched to
+    tab.html = null;
+    tab.htmlBlocks = null;
+  }
+
+  const tab = getActiveTab();
+  if (!tab || tab.hasError || tab.content === null || tab.codeTheme === theme) return;
+
+  const scrollTop = document.getElementById("content-area").scrollTop;
+  const rendered = await invoke("render_markdown", { content: tab.content, theme, path: tab.path });
+  tab.html = rendered.html;
+  tab.htmlBlocks = null;
+  tab.frontMatter = rendered.front_matter;
+  tab.blocks = rendered.blocks;
+  tab.links = rendered.links;
+  tab.codeTheme = theme;
+  tab.selectedBlocks = [];
+  tab.commentsData = JSON.parse(JSON.stringify(commentsData));
+
+  renderTabContent(tab);
+  document.getElementById("content-area").scrollTop = scrollTop;
 }
 
+systemDarkQuery.addEventListener("change", () => {
+  if (currentTheme === "system") rerenderForCodeTheme();
+});
+
 function toggleTheme() {
   const idx = THEMES.indexOf(currentTheme);
   applyTheme(THEMES[(idx + 1) % THEMES.length]);
 }
 
-function assignCommentableBlockIds() {
-  let headingIdx = 0, paraIdx = 0, listIdx = 0, codeIdx = 0, quoteIdx = 0;
+function assignCommentableBlockIds(blocks = []) {
+  let headingIdx = 0, paraIdx = 0, listIdx = 0, codeIdx = 0, quoteIdx = 0, diagramIdx = 0, outputIdx = 0;
 
   document.querySelectorAll("#content h1, #content h2, #content h3, #content h4, #content h5, #content h6").forEach((el) => {
     el.id = "mkw-heading-" + headingIdx++;
@@ -173,6 +230,111 @@ function assignCommentableBlockIds() {
     el.id = "mkw-quote-" + quoteIdx++;
     el.classList.add("commentable-block");
   });
+
+  document.querySelectorAll("#content figure.diagram").forEach((el) => {
+    el.id = "mkw-diagram-" + diagramIdx++;
+    el.classList.add("commentable-block");
+  });
+
+  document.querySelectorAll("#content .nb-outputs").forEach((el) => {
+    el.id = "mkw-output-" + outputIdx++;
+    el.classList.add("commentable-block");
+  });
+
+  // Attach the markdown line range reported by the backend for each block
+  const blocksBySourcepos = new Map(blocks.map(b => [b.sourcepos, b]));
+  document.querySelectorAll("#content .commentable-block[data-sourcepos]").forEach((el) => {
+    const block = blocksBySourcepos.get(el.dataset.sourcepos);
+    if (block) {
+      el.dataset.startLine = block.start_line;
+      el.dataset.endLine = block.end_line;
+    }
+  });
+}
+
+function blockLineRange(blockId) {
+  const block = document.getElementById(blockId);
+  if (!block || !block.dataset.startLine) return null;
+  const start = Number(block.dataset.startLine);
+  const end = Number(block.dataset.endLine);
+  return start === end ? `line ${start}` : `lines ${start}-${end}`;
+}
+
+// CSV and TSV tabs get their first page with the document; other pages and
+// sorted views come from the backend. The view survives live reloads.
+function enhanceDataTable(tab) {
+  const table = document.querySelector("#content .data-table table");
+  if (!table) return;
+
+  const view = tab.tableView || (tab.tableView = { page: 0, sort: null });
+  const total = Number(table.dataset.total);
+  const pageSize = Number(table.dataset.pageSize);
+  const pages = Math.max(1, Math.ceil(total / pageSize));
+  const headers = [...table.querySelectorAll("th[data-column]")];
+  const numeric = headers.map(th => th.classList.contains("numeric"));
+  const status = table.parentElement.querySelector(".data-table-status");
+
+  const pager = document.createElement("div");
+  pager.className = "data-table-pager";
+  const prev = document.createElement("button");
+  prev.textContent = "‹ Previous";
+  const next = document.createElement("button");
+  next.textContent = "Next ›";
+  pager.append(prev, next);
+  if (pages > 1) table.parentElement.appendChild(pager);
+
+  const update = () => {
+    headers.forEach((th) => {
+      const sorted = view.sort && view.sort.column === Number(th.dataset.column);
+      th.setAttribute("aria-sort", sorted ? (view.sort.descending ? "descending" : "ascending") : "none");
+    });
+    const first = view.page * pageSize;
+    const last = Math.min(first + pageSize, total);
+    status.textContent = pages > 1
+      ? `Rows ${first + 1}–${last} of ${total} · page ${view.page + 1} of ${pages}`
+      : `${total} row${total === 1 ? "" : "s"}`;
+    prev.disabled = view.page === 0;
+    next.disabled = view.page >= pages - 1;
+  };
+
+  const load = async () => {
+    view.page = Math.min(view.page, pages - 1);
+    try {
+      const page = await invoke("read_table_page", { path: tab.path, page: view.page, sort: view.sort });
+      table.tBodies[0].replaceChildren(...page.rows.map((row) => {
+        const tr = document.createElement("tr");
+        numeric.forEach((isNumeric, i) => {
+          const td = document.createElement("td");
+          td.textContent = row[i] ?? "";
+          if (isNumeric) td.className = "numeric";
+          tr.appendChild(td);
+        });
+        return tr;
+      }));
+    } catch (e) {
+      console.error("Failed to load table page:", e);
+    }
+    update();
+  };
+
+  headers.forEach((th) => {
+    th.onclick = () => {
+      const column = Number(th.dataset.column);
+      view.sort = view.sort && view.sort.column === column
+        ? { column, descending: !view.sort.descending }
+        : { column, descending: false };
+      view.page = 0;
+      load();
+    };
+  });
+  prev.onclick = () => { view.page--; load(); };
+  next.onclick = () => { view.page++; load(); };
+
+  if (view.page > 0 || view.sort) {
+    load();
+  } else {
+    update();
+  }
 }
 
 async function loadFileHistory() {
@@ -192,10 +354,11 @@ async function addToHistory(path) {
   }
 }
 
-async function openFileInNewTab(path) {
+async function openFileInNewTab(path, fragment = null) {
   const existing = getTabByPath(path);
   if (existing) {
     switchToTab(existing.id);
+    if (fragment) scrollToFragment(existing, fragment);
     return;
   }
 
@@ -207,33 +370,161 @@ async function openFileInNewTab(path) {
   await addToHistory(path);
 
   updateTabBarUI();
+  if (fragment) scrollToFragment(tab, fragment);
+}
+
+// `#L12` and `#L10-L20` point at lines of a source file tab.
+function parseLineRange(fragment) {
+  const match = /^L(\d+)(?:-L?(\d+))?$/.exec(fragment || "");
+  if (!match) return null;
+  const start = Number(match[1]);
+  const end = match[2] ? Number(match[2]) : start;
+  return { start: Math.min(start, end), end: Math.max(start, end) };
+}
+
+function highlightLineRange(range) {
+  document.querySelectorAll("#content .line.highlighted").forEach(el => el.classList.remove("highlighted"));
+  if (!range) return null;
+  let first = null;
+  for (let n = range.start; n <= range.end; n++) {
+    const line = document.getElementById("L" + n);
+    if (!line) break;
+    line.classList.add("highlighted");
+    first = first || line;
+  }
+  return first;
+}
+
+function scrollToFragment(tab, fragment) {
+  const range = parseLineRange(fragment);
+  if (range && document.querySelector("#content pre.numbered")) {
+    tab.lineRange = range;
+    const first = highlightLineRange(range);
+    if (first) first.scrollIntoView({ behavior: "smooth", block: "center" });
+    return;
+  }
+
+  // With raw HTML on, ids in the document are prefixed by the sanitizer.
+  const byId = (id) => document.querySelector(`#content [id="${CSS.escape(id)}"]`);
+  const heading = (tab.headings || []).find(h => h.slug === fragment);
+  const target = heading
+    ? document.getElementById("mkw-heading-" + heading.index)
+    : byId(fragment) || byId("user-content-" + fragment);
+  if (target) target.scrollIntoView({ behavior: "smooth", block: "start" });
+}
+
+// Task list items in markdown files can be ticked; the change is written
+// back to the file, unless it was changed elsewhere since it was rendered.
+// In edit mode the editor owns the source, so they stay read-only there.
+function enableTaskCheckboxes(tab) {
+  if (!isMarkdownTab(tab) || tab.editing) return;
+  document.querySelectorAll('#content li[data-sourcepos] > input[type="checkbox"]:not(.task-checkbox)').forEach((checkbox) => {
+    checkbox.disabled = false;
+    checkbox.classList.add("task-checkbox");
+    checkbox.addEventListener("change", () => toggleTask(tab, checkbox));
+  });
+}
+
+async function toggleTask(tab, checkbox) {
+  const line = parseInt(checkbox.parentElement.dataset.sourcepos, 10);
+  checkbox.disabled = true;
+  try {
+    const result = await invoke("toggle_task", { path: tab.path, line, expectedHash: tab.fileHash });
+    tab.fileHash = result.hash;
+    checkbox.checked = result.checked;
+    checkbox.disabled = false;
+  } catch (e) {
+    console.error("Failed to toggle task:", e);
+    await message(String(e), { title: "Task list", kind: "warning" });
+    await loadFileIntoTab(tab.id, tab.path);
+  }
+}
+
+function markDocumentLinks(links) {
+  const byHref = new Map(links.map(link => [link.href, link]));
+  document.querySelectorAll("#content a[href]").forEach((a) => {
+    const link = byHref.get(a.getAttribute("href"));
+    if (link) {
+      a.dataset.linkKind = link.kind;
+      if (link.path) a.title = a.title || link.path;
+    }
+  });
 }
 
-async function loadFileIntoTab(tabId, path) {
+async function followLink(a) {
+  const tab = getActiveTab();
+  const href = a.getAttribute("href");
+  const link = tab && (tab.links || []).find(l => l.href === href);
+
+  if (!link) {
+    if (/^(https?:|mailto:)/i.test(href)) {
+      await invoke("open_external_url", { url: href });
+    }
+    return;
+  }
+
+  if (link.kind === "anchor" || (link.kind === "markdown" && link.path === tab.path)) {
+    if (link.fragment) scrollToFragment(tab, link.fragment);
+  } else if (link.kind === "markdown") {
+    await openFileInNewTab(link.path, link.fragment);
+  } else {
+    await invoke("open_with_system", { path: link.path });
+  }
+}
+
+// With `inPlace`, a view showing an earlier render of the file is patched
+// with the blocks that changed rather than rendered again.
+async function loadFileIntoTab(tabId, path, inPlace = false) {
   const tab = tabs.find(t => t.id === tabId);
   if (!tab) return;
 
   try {
-    const content = await invoke("read_file", { path });
-    const html = await invoke("render_markdown", { content });
-    const headings = await invoke("extract_headings", { markdown: content });
+    const codeTheme = resolvedCodeTheme();
+    const patchable = inPlace && tab.htmlBlocks && !tab.editing && !tab.hasError && tab.codeTheme === codeTheme;
+    const rendered = await invoke("render_file", {
+      path,
+      theme: codeTheme,
+      since: patchable ? tab.fileHash : null,
+    });
+    const content = rendered.content;
+    const outline = await invoke("extract_outline", { markdown: content, markdownPath: path });
+    const changes = rendered.html ? null : rendered.changes;
 
     tab.content = content;
-    tab.html = html;
-    tab.headings = headings;
+    if (tab.hasError) {
+      // The file is back, e.g. re-created after a deletion.
+      tab.hasError = false;
+      tab.displayName = tabDisplayName(path);
+    }
+    if (tab.editing && !tab.dirty) tab.draft = content;
+    tab.htmlBlocks = changes ? applyBlockChanges(tab.htmlBlocks, changes) : rendered.html;
+    tab.html = joinBlocks(tab.htmlBlocks);
+    tab.frontMatter = rendered.front_matter;
+    tab.blocks = rendered.blocks;
+    tab.links = rendered.links;
+    tab.codeTheme = codeTheme;
+    tab.outline = outline;
+    tab.headings = flattenOutline(outline.sections);
+
+    await lintTab(tab);
 
     tab.commentsData = await invoke("load_comments", { markdownPath: path });
-    const currentHash = await invoke("hash_file", { path });
+    const currentHash = rendered.hash;
 
     if (tab.commentsData.file_hash && tab.commentsData.file_hash !== currentHash) {
       showStaleCommentsBanner();
     }
     tab.commentsData.file_hash = currentHash;
+    tab.fileHash = currentHash;
 
-    await invoke("watch_file", { path });
+    tab.watch = await invoke("watch_file", { path });
 
     if (tabId === activeTabId) {
-      renderTabContent(tab);
+      if (changes) {
+        patchTabContent(tab, changes);
+      } else {
+        renderTabContent(tab);
+      }
     }
 
     tab.lastAccessed = Date.now();
@@ -255,6 +546,8 @@ async function loadFileIntoTab(tabId, path) {
 function showErrorState(tabId, path) {
   // Clear outline for error state
   document.getElementById("outline-list").innerHTML = "";
+  renderDiagnostics(null);
+  showEditor(null);
 
   // Show error state
   const errorDiv = document.createElement("div");
@@ -272,6 +565,7 @@ function showErrorState(tabId, path) {
 
   document.getElementById("content").innerHTML = "";
   document.getElementById("content").appendChild(errorDiv);
+  renderMetadataPanel(null);
 
   document.body.classList.remove("no-file");
   document.body.classList.add("file-error");
@@ -280,8 +574,55 @@ function showErrorState(tabId, path) {
   document.getElementById("toolbar-info").style.display = "flex";
 }
 
+function renderMetadataPanel(frontMatter) {
+  const panel = document.getElementById("metadata-panel");
+  panel.innerHTML = "";
+
+  if (!frontMatter) {
+    panel.style.display = "none";
+    return;
+  }
+
+  if (frontMatter.error) {
+    const error = document.createElement("div");
+    error.className = "metadata-error";
+    error.textContent = `Invalid ${frontMatter.format.toUpperCase()} front matter: ${frontMatter.error}`;
+    panel.appendChild(error);
+  }
+
+  const data = frontMatter.data;
+  if (data && typeof data === "object" && !Array.isArray(data)) {
+    const list = document.createElement("dl");
+    Object.entries(data).forEach(([key, value]) => {
+      const term = document.createElement("dt");
+      term.textContent = key;
+
+      const details = document.createElement("dd");
+      if (Array.isArray(value)) {
+        value.forEach(item => {
+          const tag = document.createElement("span");
+          tag.className = "metadata-tag";
+          tag.textContent = typeof item === "object" ? JSON.stringify(item) : String(item);
+          details.appendChild(tag);
+        });
+      } else {
+        details.textContent = value !== null && typeof value === "object"
+          ? JSON.stringify(value)
+          : String(value);
+      }
+
+      list.appendChild(term);
+      list.appendChild(details);
+    });
+    panel.appendChild(list);
+  }
+
+  panel.style.display = panel.childElementCount > 0 ? "block" : "none";
+}
+
 function renderTabContent(tab) {
   document.getElementById("content").innerHTML = tab.html;
+  renderMetadataPanel(tab.frontMatter);
 
   selectedBlocks = [];
   const addBtn = document.getElementById("bottom-bar-add-comment");
@@ -291,10 +632,11 @@ function renderTabContent(tab) {
   }
   hideStaleCommentsBanner();
 
-  assignCommentableBlockIds();
+  decorateContent(tab);
+  enhanceDataTable(tab);
+  showEditor(tab);
 
-  hljs.highlightAll();
-  populateOutline(tab.headings);
+  populateOutline(tab.outline);
 
   document.body.classList.remove("no-file");
   document.body.classList.remove("file-error");
@@ -314,9 +656,125 @@ function renderTabContent(tab) {
   }
 }
 
+// What the rendered document gets once it's in the DOM, whole or patched.
+function decorateContent(tab) {
+  assignCommentableBlockIds(tab.blocks);
+  enableTaskCheckboxes(tab);
+  markDocumentLinks(tab.links);
+  highlightLineRange(tab.lineRange);
+  renderDiagnostics(tab);
+}
+
+// Blocks of a file's render are separated by this comment in the DOM, so the
+// ones that changed can be found and replaced in place.
+const BLOCK_MARKER = "<!--block-->";
+
+function joinBlocks(blocks) {
+  return blocks.map(html => BLOCK_MARKER + html).join("");
+}
+
+// Moves a `data-sourcepos` value like "12:1-14:3" `lines` down the file.
+function shiftSourcepos(sourcepos, lines) {
+  return sourcepos.replace(/^(\d+):(\d+)-(\d+):(\d+)$/, (_, l1, c1, l2, c2) =>
+    `${Number(l1) + lines}:${c1}-${Number(l2) + lines}:${c2}`);
+}
+
+// The blocks of the new render, from the old blocks and the changes between
+// them. Changes name old blocks by index.
+function applyBlockChanges(blocks, changes) {
+  const head = [];
+  const slots = blocks.map(html => [html]); // an old block, then blocks added after it
+  for (const change of changes) {
+    if (change.kind === "added") {
+      (change.after === null ? head : slots[change.after]).push(change.html);
+    } else if (change.kind === "modified") {
+      slots[change.index][0] = change.html;
+    } else if (change.kind === "moved") {
+      slots[change.index][0] = slots[change.index][0].replace(/data-sourcepos="([^"]*)"/g,
+        (_, sourcepos) => `data-sourcepos="${shiftSourcepos(sourcepos, change.lines)}"`);
+    } else if (change.kind === "removed") {
+      slots[change.index][0] = null;
+    }
+  }
+  return head.concat(...slots).filter(html => html !== null);
+}
+
+// Applies the changes between two renders of the active tab's file to the
+// DOM, and marks the blocks that were added or modified.
+function patchTabContent(tab, changes) {
+  const content = document.getElementById("content");
+  const groups = []; // the nodes of each old block, its marker first
+  content.childNodes.forEach((node) => {
+    if (node.nodeType === Node.COMMENT_NODE && node.data === "block") {
+      groups.push([node]);
+    } else if (groups.length > 0) {
+      groups[groups.length - 1].push(node);
+    }
+  });
+  // A view that isn't the render the changes were made against is
+  // rendered whole instead.
+  const known = (index) => index === null || (index >= 0 && index < groups.length);
+  if (groups.length === 0 || !changes.every(c => known(c.kind === "added" ? c.after : c.index))) {
+    renderTabContent(tab);
+    return;
+  }
+
+  const changed = [];
+  const parse = (html) => {
+    const template = document.createElement("template");
+    template.innerHTML = BLOCK_MARKER + html;
+    const nodes = [...template.content.childNodes];
+    changed.push(...nodes.filter(node => node.nodeType === Node.ELEMENT_NODE));
+    return nodes;
+  };
+  const lastAdded = new Map(); // old block index -> last node added after it
+  const removed = [];
+
+  for (const change of changes) {
+    if (change.kind === "added") {
+      const nodes = parse(change.html);
+      const anchor = lastAdded.get(change.after)
+        || (change.after === null ? null : groups[change.after][groups[change.after].length - 1]);
+      if (anchor) {
+        anchor.after(...nodes);
+      } else {
+        content.prepend(...nodes);
+      }
+      lastAdded.set(change.after, nodes[nodes.length - 1]);
+    } else if (change.kind === "modified") {
+      const nodes = parse(change.html);
+      const group = groups[change.index];
+      group[0].before(...nodes);
+      group.forEach(node => node.remove());
+      groups[change.index] = nodes;
+    } else if (change.kind === "moved") {
+      groups[change.index].forEach((node) => {
+        if (node.nodeType !== Node.ELEMENT_NODE) return;
+        [node, ...node.querySelectorAll("[data-sourcepos]")].forEach((el) => {
+          if (el.dataset.sourcepos) el.dataset.sourcepos = shiftSourcepos(el.dataset.sourcepos, change.lines);
+        });
+      });
+    } else if (change.kind === "removed") {
+      removed.push(change.index);
+    }
+  }
+  removed.forEach(index => groups[index].forEach(node => node.remove()));
+
+  renderMetadataPanel(tab.frontMatter);
+  decorateContent(tab);
+  populateOutline(tab.outline);
+  renderCommentBadges();
+
+  changed.forEach((el) => {
+    el.classList.add("block-changed");
+    el.addEventListener("animationend", () => el.classList.remove("block-changed"), { once: true });
+  });
+}
+
 function updateTabBarUI() {
   const tabScroll = document.querySelector(".tab-scroll");
   if (!tabScroll) return;
+  markActiveWorkspaceFile();
 
   tabScroll.innerHTML = "";
 
@@ -332,6 +790,20 @@ function updateTabBarUI() {
     tabName.className = "tab-name";
     tabName.textContent = tab.displayName;
 
+    const tabWatch = document.createElement("button");
+    tabWatch.className = "tab-watch";
+    if (tab.watch) {
+      tabWatch.dataset.mode = tab.watch.mode;
+      tabWatch.textContent = tab.watch.mode === "poll" ? "poll" : "•";
+      tabWatch.title = watchModeLabel(tab.watch);
+    } else {
+      tabWatch.hidden = true;
+    }
+    tabWatch.onclick = (e) => {
+      e.stopPropagation();
+      openWatchSettings(tab);
+    };
+
     const tabClose = document.createElement("button");
     tabClose.className = "tab-close";
     tabClose.textContent = "×";
@@ -342,6 +814,7 @@ function updateTabBarUI() {
     };
 
     tabItem.appendChild(tabName);
+    tabItem.appendChild(tabWatch);
     tabItem.appendChild(tabClose);
 
     tabItem.onclick = () => {
@@ -389,6 +862,7 @@ function switchToTab(tabId) {
 async function closeTab(tabId) {
   const tab = tabs.find(t => t.id === tabId);
   if (!tab) return;
+  if (!(await confirmDiscardDraft(tab))) return;
 
   tabs = tabs.filter(t => t.id !== tabId);
 
@@ -422,6 +896,9 @@ function closeAllTabs() {
   document.getElementById("toolbar-info").style.display = "none";
   document.getElementById("content").innerHTML = "";
   document.getElementById("outline-list").innerHTML = "";
+  renderDiagnostics(null);
+  showEditor(null);
+  renderMetadataPanel(null);
   hideBottomBar();
 }
 
@@ -510,14 +987,44 @@ async function clearHistory() {
 
 let headingObserver = null;
 
-function populateOutline(headings) {
+function flattenOutline(sections) {
+  return sections.flatMap((s) => [s, ...flattenOutline(s.children)]);
+}
+
+function sectionSummary(s) {
+  const lines = s.start_line === s.end_line ? `line ${s.start_line}` : `lines ${s.start_line}-${s.end_line}`;
+  const parts = [lines, `${s.word_count} words`];
+  if (s.tasks_total > 0) parts.push(`${s.tasks_done}/${s.tasks_total} tasks`);
+  if (s.unresolved_comments > 0) parts.push(`${s.unresolved_comments} unresolved`);
+  return parts.join(", ");
+}
+
+function populateOutline(outline) {
   const list = document.getElementById("outline-list");
   list.innerHTML = "";
+  const headings = flattenOutline(outline.sections);
   headings.forEach((h) => {
     const li = document.createElement("li");
-    li.textContent = h.text;
+    const label = document.createElement("span");
+    label.className = "outline-text";
+    label.textContent = h.text;
+    li.appendChild(label);
+    if (h.tasks_total > 0) {
+      const tasks = document.createElement("span");
+      tasks.className = "outline-stat" + (h.tasks_done === h.tasks_total ? " done" : "");
+      tasks.textContent = `${h.tasks_done}/${h.tasks_total}`;
+      li.appendChild(tasks);
+    }
+    if (h.unresolved_comments > 0) {
+      const comments = document.createElement("span");
+      comments.className = "outline-stat comments";
+      comments.textContent = h.unresolved_comments;
+      li.appendChild(comments);
+    }
     li.dataset.level = h.level;
     li.dataset.headingIndex = h.index;
+    li.dataset.slug = h.slug;
+    li.title = `${h.text} (${sectionSummary(h)})`;
     li.addEventListener("click", () => {
       const el = document.getElementById("mkw-heading-" + h.index);
       if (el) el.scrollIntoView({ behavior: "smooth", block: "start" });
@@ -528,6 +1035,82 @@ function populateOutline(headings) {
   setupScrollTracking(headings);
 }
 
+// Lint runs with every load, so it follows the file watcher like the
+// rendered view does.
+async function lintTab(tab) {
+  try {
+    tab.diagnostics = await invoke("lint_document", { path: tab.path });
+    tab.lintError = null;
+  } catch (e) {
+    tab.diagnostics = [];
+    tab.lintError = String(e);
+  }
+}
+
+// The innermost commentable block containing a line.
+function blockAtLine(line) {
+  let best = null;
+  document.querySelectorAll("#content .commentable-block[data-start-line]").forEach((el) => {
+    const start = Number(el.dataset.startLine);
+    const end = Number(el.dataset.endLine);
+    if (start <= line && line <= end && (!best || end - start < best.end - best.start)) {
+      best = { el, start, end };
+    }
+  });
+  return best && best.el;
+}
+
+function renderDiagnostics(tab) {
+  const panel = document.getElementById("lint-panel");
+  const list = document.getElementById("lint-list");
+  list.innerHTML = "";
+
+  // A patched document keeps the marks of blocks it didn't replace.
+  document.querySelectorAll("#content .lint-error, #content .lint-warning").forEach((block) => {
+    block.classList.remove("lint-error", "lint-warning");
+    block.removeAttribute("title");
+  });
+
+  const diagnostics = tab ? tab.diagnostics : [];
+  const error = tab ? tab.lintError : null;
+  panel.style.display = diagnostics.length || error ? "" : "none";
+  document.getElementById("lint-count").textContent = diagnostics.length || "";
+
+  if (error) {
+    const li = document.createElement("li");
+    li.className = "lint-item lint-config-error";
+    li.textContent = error;
+    list.appendChild(li);
+  }
+
+  diagnostics.forEach((d) => {
+    const block = blockAtLine(d.start_line);
+    if (block) {
+      block.classList.add("lint-" + d.severity);
+      block.title = block.title ? `${block.title}\n${d.message}` : d.message;
+    }
+
+    const li = document.createElement("li");
+    li.className = "lint-item lint-" + d.severity;
+    li.title = `${d.rule}: ${d.message}`;
+    const line = document.createElement("span");
+    line.className = "lint-line";
+    line.textContent = d.start_line === d.end_line ? `L${d.start_line}` : `L${d.start_line}-${d.end_line}`;
+    const message = document.createElement("span");
+    message.className = "lint-message";
+    message.textContent = d.message;
+    li.append(line, message);
+    if (block) {
+      li.addEventListener("click", () => {
+        block.scrollIntoView({ behavior: "smooth", block: "center" });
+        block.classList.add("lint-flash");
+        setTimeout(() => block.classList.remove("lint-flash"), 1200);
+      });
+    }
+    list.appendChild(li);
+  });
+}
+
 function setupScrollTracking(headings) {
   if (headingObserver) {
     headingObserver.disconnect();
@@ -596,14 +1179,535 @@ function setupScrollTracking(headings) {
   });
 }
 
+// Folder workspaces: the documents under a folder, as a tree in the sidebar
+
+let workspace = null; // { root, tree }
+const collapsedFolders = new Set();
+let workspaceRescan = null;
+
+async function openFolderDialog() {
+  const path = await open({ directory: true });
+  if (path) openWorkspace(path);
+}
+
+// Opens a path from the command line, the dock or another instance, which
+// may be a file or a folder.
+async function openPath(path) {
+  if (await invoke("is_folder", { path })) {
+    await openWorkspace(path);
+  } else {
+    await openFileInNewTab(path);
+  }
+}
+
+async function openWorkspace(path) {
+  let tree;
+  try {
+    tree = await invoke("open_workspace", { path });
+  } catch (e) {
+    await message(String(e), { title: "Open Folder", kind: "error" });
+    return;
+  }
+  workspace = { root: tree.path, tree };
+  collapsedFolders.clear();
+  document.body.classList.add("has-workspace");
+  renderWorkspaceTree();
+
+  // Start on the folder's README, as a repository page would.
+  const readme = tree.children.find(e => !e.children && /^(readme|index)\.md$/i.test(e.name));
+  if (readme && tabs.length === 0) await openFileInNewTab(readme.path);
+}
+
+async function closeWorkspace() {
+  await invoke("close_workspace").catch((e) => console.error("Failed to close folder:", e));
+  workspace = null;
+  document.body.classList.remove("has-workspace");
+  renderWorkspaceTree();
+}
+
+function renderWorkspaceTree() {
+  const panel = document.getElementById("workspace-panel");
+  const list = document.getElementById("workspace-tree");
+  list.innerHTML = "";
+  panel.style.display = workspace ? "" : "none";
+  if (!workspace) return;
+
+  document.getElementById("workspace-name").textContent = workspace.tree.name;
+  document.getElementById("workspace-name").title = workspace.root;
+  if (workspace.tree.children.length === 0) {
+    const li = document.createElement("li");
+    li.className = "ws-empty";
+    li.textContent = "No documents";
+    list.appendChild(li);
+  }
+  workspace.tree.children.forEach(entry => list.appendChild(workspaceEntry(entry, 0)));
+  markActiveWorkspaceFile();
+}
+
+function workspaceEntry(entry, depth) {
+  const li = document.createElement("li");
+  const row = document.createElement("div");
+  row.className = "ws-row";
+  row.style.paddingLeft = 8 + depth * 12 + "px";
+  row.textContent = entry.name;
+  row.title = entry.path;
+  li.appendChild(row);
+
+  if (entry.children) {
+    const collapsed = collapsedFolders.has(entry.path);
+    li.className = "ws-folder" + (collapsed ? " collapsed" : "");
+    const children = document.createElement("ul");
+    entry.children.forEach(child => children.appendChild(workspaceEntry(child, depth + 1)));
+    li.appendChild(children);
+    row.addEventListener("click", () => {
+      li.classList.toggle("collapsed");
+      if (li.classList.contains("collapsed")) {
+        collapsedFolders.add(entry.path);
+      } else {
+        collapsedFolders.delete(entry.path);
+      }
+    });
+  } else {
+    li.className = "ws-file";
+    li.dataset.path = entry.path;
+    row.addEventListener("click", () => openFileInNewTab(entry.path));
+  }
+  return li;
+}
+
+function markActiveWorkspaceFile() {
+  const tab = getActiveTab();
+  document.querySelectorAll("#workspace-tree .ws-file").forEach((li) => {
+    li.classList.toggle("active", !!tab && li.dataset.path === tab.path);
+  });
+}
+
+listen("workspace-changed", () => {
+  if (!workspace) return;
+  // Saves and checkouts arrive as bursts of events; scan once they settle.
+  clearTimeout(workspaceRescan);
+  workspaceRescan = setTimeout(async () => {
+    try {
+      workspace.tree = await invoke("scan_workspace", { path: workspace.root });
+      renderWorkspaceTree();
+    } catch (e) {
+      console.error("Failed to scan folder:", e);
+    }
+  }, 150);
+});
+
 async function openFileDialog() {
+  const formats = await invoke("document_formats");
   const path = await open({
     multiple: false,
-    filters: [{ name: "Markdown", extensions: ["md", "markdown"] }],
+    filters: [
+      { name: "All documents", extensions: formats.flatMap(f => f.extensions) },
+      ...formats,
+    ],
   });
   if (path) openFileInNewTab(path);
 }
 
+// Render profile (Markdown extensions)
+
+const RENDER_EXTENSIONS = [
+  ["table", "Tables"],
+  ["tasklist", "Task lists"],
+  ["strikethrough", "Strikethrough"],
+  ["autolink", "Autolinks"],
+  ["footnotes", "Footnotes"],
+  ["alerts", "Alerts (> [!NOTE])"],
+  ["math", "Math ($...$)"],
+  ["superscript", "Superscript (^x^)"],
+  ["subscript", "Subscript (~x~)"],
+  ["header_ids", "Heading anchors"],
+  ["description_lists", "Description lists"],
+  ["wikilinks", "Wikilinks ([[page]])"],
+];
+
+let renderProfile = null;
+let markdownExtensions = [];
+
+function renderProfileForm() {
+  document.getElementById("render-preset-select").value = renderProfile.preset;
+
+  const list = document.getElementById("render-extension-list");
+  list.innerHTML = "";
+  RENDER_EXTENSIONS.forEach(([key, label]) => {
+    const row = document.createElement("label");
+    row.className = "modal-checkbox";
+
+    const checkbox = document.createElement("input");
+    checkbox.type = "checkbox";
+    checkbox.checked = !!renderProfile[key];
+    checkbox.addEventListener("change", async () => {
+      renderProfile = { ...renderProfile, [key]: checkbox.checked, preset: "custom" };
+      document.getElementById("render-preset-select").value = "custom";
+      await saveRenderProfile();
+    });
+
+    row.appendChild(checkbox);
+    row.appendChild(document.createTextNode(label));
+    list.appendChild(row);
+  });
+
+  // Raw HTML is independent of the presets
+  const rawHtml = document.getElementById("render-raw-html");
+  rawHtml.checked = !!renderProfile.raw_html;
+}
+
+async function saveRenderProfile() {
+  try {
+    await invoke("set_render_profile", { profile: renderProfile });
+    await rerenderAllTabs();
+  } catch (e) {
+    console.error("Failed to save render profile:", e);
+  }
+}
+
+async function rerenderAllTabs() {
+  for (const tab of tabs) {
+    if (tab.id !== activeTabId) {
+      tab.html = null;
+      tab.htmlBlocks = null;
+    }
+  }
+  const tab = getActiveTab();
+  if (tab && !tab.hasError) {
+    const scrollTop = document.getElementById("content-area").scrollTop;
+    await loadFileIntoTab(tab.id, tab.path);
+    document.getElementById("content-area").scrollTop = scrollTop;
+  }
+}
+
+async function openRenderSettings() {
+  try {
+    renderProfile = await invoke("get_render_profile");
+    renderProfileForm();
+    showModal("render-settings-modal");
+  } catch (e) {
+    console.error("Failed to load render profile:", e);
+  }
+}
+
+function watchModeLabel(watch) {
+  if (watch.mode === "poll") {
+    return `Live reload: checking for changes every ${watch.poll_interval_ms / 1000} s`;
+  }
+  return "Live reload: file system events";
+}
+
+let watchSettingsTab = null;
+
+async function openWatchSettings(tab) {
+  if (!tab.watch) return;
+  try {
+    const settings = await invoke("get_watch_settings");
+    watchSettingsTab = tab;
+    document.getElementById("watch-settings-status").textContent = watchModeLabel(tab.watch);
+    document.getElementById("watch-settings-folder").textContent = tab.watch.folder;
+    document.getElementById("watch-folder-mode").value = tab.watch.setting || "";
+    document.getElementById("watch-poll-interval").value = settings.poll_interval_ms / 1000;
+    showModal("watch-settings-modal");
+  } catch (e) {
+    console.error("Failed to load watch settings:", e);
+  }
+}
+
+// Asks for every tab's watch status again after the settings changed.
+async function refreshWatchModes() {
+  for (const tab of tabs) {
+    if (tab.hasError) continue;
+    tab.watch = await invoke("watch_file", { path: tab.path }).catch(() => tab.watch);
+  }
+  updateTabBarUI();
+  if (watchSettingsTab && watchSettingsTab.watch) {
+    document.getElementById("watch-settings-status").textContent = watchModeLabel(watchSettingsTab.watch);
+  }
+}
+
+async function exportActiveTab() {
+  const tab = getActiveTab();
+  if (!tab || tab.hasError) return;
+
+  const baseName = tab.path.split(/[\\/]/).pop().replace(/\.[^.]+$/, "");
+  const outputPath = await save({
+    defaultPath: baseName + ".html",
+    filters: [
+      { name: "HTML", extensions: ["html"] },
+      { name: "PDF", extensions: ["pdf"] },
+      { name: "Review report (Markdown)", extensions: ["md"] },
+    ],
+  });
+  if (!outputPath) return;
+
+  try {
+    if (/\.md$/i.test(outputPath)) {
+      await invoke("export_review_report", { path: tab.path, outputPath });
+      return;
+    }
+
+    const count = tab.path === currentPath ? commentsData.comments.length : 0;
+    const includeComments = count > 0 && await confirm(
+      `Include the ${count} review comment${count === 1 ? "" : "s"} in the export?`,
+      { title: "Export", kind: "info", okLabel: "Include", cancelLabel: "Leave out" }
+    );
+    if (/\.pdf$/i.test(outputPath)) {
+      const warning = await invoke("export_pdf", { path: tab.path, outputPath, includeComments });
+      if (warning) await message(warning, { title: "Export", kind: "warning" });
+    } else {
+      await invoke("export_html", { path: tab.path, outputPath, theme: resolvedCodeTheme(), includeComments });
+    }
+  } catch (e) {
+    console.error("Failed to export:", e);
+    await message(`Export failed: ${e}`, { title: "Export", kind: "error" });
+  }
+}
+
+function openLinkCheck() {
+  const tab = getActiveTab();
+  document.getElementById("link-check-document").disabled = !tab || tab.hasError;
+  showModal("link-check-modal");
+}
+
+async function runLinkCheck(path) {
+  const status = document.getElementById("link-check-status");
+  const list = document.getElementById("link-check-results");
+  list.innerHTML = "";
+  status.textContent = "Checking…";
+
+  let broken;
+  try {
+    broken = await invoke("check_links", { path });
+  } catch (e) {
+    status.textContent = `Link check failed: ${e}`;
+    return;
+  }
+  status.textContent = broken.length === 0
+    ? "No broken links."
+    : `${broken.length} broken link${broken.length === 1 ? "" : "s"}`;
+
+  // Folder results are listed relative to the folder.
+  const base = path.replace(/[\\/]+$/, "");
+  broken.forEach((link) => {
+    const li = document.createElement("li");
+    li.className = "lint-item lint-error";
+    li.title = link.target;
+    const where = document.createElement("span");
+    where.className = "lint-line";
+    const name = link.document.startsWith(base + "/") || link.document.startsWith(base + "\\")
+      ? link.document.slice(base.length + 1)
+      : link.document.split(/[\\/]/).pop();
+    where.textContent = `${name}:${link.line}`;
+    const text = document.createElement("span");
+    text.className = "lint-message";
+    text.textContent = link.message;
+    li.append(where, text);
+    li.addEventListener("click", async () => {
+      hideModal("link-check-modal");
+      await openFileInNewTab(link.document);
+      const block = blockAtLine(link.line);
+      if (block) {
+        block.scrollIntoView({ behavior: "smooth", block: "center" });
+        block.classList.add("lint-flash");
+        setTimeout(() => block.classList.remove("lint-flash"), 1200);
+      }
+    });
+    list.appendChild(li);
+  });
+}
+
+// Edit mode: markdown source on the left, live preview on the right
+
+function isMarkdownTab(tab) {
+  return !!tab && !tab.hasError && markdownExtensions.includes(tab.path.split(".").pop().toLowerCase());
+}
+
+function showEditor(tab) {
+  const editing = !!tab && tab.editing;
+  document.body.classList.toggle("editing", editing);
+  document.getElementById("btn-edit").classList.toggle("active", editing);
+  document.getElementById("btn-edit").disabled = !isMarkdownTab(tab);
+  if (!editing) return;
+
+  const editor = document.getElementById("editor");
+  if (editor.value !== tab.draft) editor.value = tab.draft;
+  updateEditorStatus(tab);
+}
+
+function updateEditorStatus(tab) {
+  const status = document.getElementById("editor-status");
+  status.classList.toggle("conflict", tab.diskChanged);
+  if (tab.diskChanged) {
+    status.textContent = "Changed on disk since it was opened. Saving will ask before overwriting.";
+  } else {
+    status.textContent = tab.dirty ? "Unsaved changes — ⌘S to save" : "Saved";
+  }
+}
+
+async function toggleEditMode() {
+  const tab = getActiveTab();
+  if (!isMarkdownTab(tab)) return;
+
+  if (tab.editing) {
+    if (!(await confirmDiscardDraft(tab))) return;
+    tab.editing = false;
+    tab.draft = null;
+    if (tab.dirty) {
+      tab.dirty = false;
+      tab.diskChanged = false;
+      await loadFileIntoTab(tab.id, tab.path);
+    }
+  } else {
+    tab.editing = true;
+    tab.draft = tab.content;
+  }
+  showEditor(tab);
+  if (tab.editing) document.getElementById("editor").focus();
+}
+
+// Offers to save unsaved edits; false when the user cancels.
+async function confirmDiscardDraft(tab) {
+  if (!tab.editing || !tab.dirty) return true;
+  const keep = await confirm(`Save changes to ${tab.path.split(/[\\/]/).pop()}?`, {
+    title: "Unsaved changes",
+    kind: "warning",
+    okLabel: "Save",
+    cancelLabel: "Discard",
+  });
+  return keep ? saveDraft(tab) : true;
+}
+
+// Writes the draft to disk; true when it was saved.
+async function saveDraft(tab, expectedHash = tab.fileHash) {
+  try {
+    tab.fileHash = await invoke("write_file", { path: tab.path, content: tab.draft, expectedHash });
+  } catch (e) {
+    if (!String(e).startsWith("Conflict")) {
+      await message(`Save failed: ${e}`, { title: "Save", kind: "error" });
+      return false;
+    }
+    const overwrite = await confirm(`${e}. Overwrite it with your version?`, {
+      title: "Save",
+      kind: "warning",
+      okLabel: "Overwrite",
+      cancelLabel: "Cancel",
+    });
+    if (!overwrite) return false;
+    return saveDraft(tab, await invoke("hash_file", { path: tab.path }));
+  }
+
+  tab.content = tab.draft;
+  tab.dirty = false;
+  tab.diskChanged = false;
+  await lintTab(tab);
+  if (tab.id === activeTabId) {
+    renderDiagnostics(tab);
+    updateEditorStatus(tab);
+  }
+  return true;
+}
+
+let previewTimer = null;
+
+async function updatePreview(tab) {
+  try {
+    const codeTheme = resolvedCodeTheme();
+    const rendered = await invoke("render_markdown", { content: tab.draft, theme: codeTheme, path: tab.path });
+    const outline = await invoke("extract_outline", { markdown: tab.draft, markdownPath: tab.path });
+    tab.html = rendered.html;
+    tab.htmlBlocks = null;
+    tab.frontMatter = rendered.front_matter;
+    tab.blocks = rendered.blocks;
+    tab.links = rendered.links;
+    tab.codeTheme = codeTheme;
+    tab.outline = outline;
+    tab.headings = flattenOutline(outline.sections);
+  } catch (e) {
+    console.error("Failed to render preview:", e);
+    return;
+  }
+  if (tab.id !== activeTabId || !tab.editing) return;
+
+  const contentArea = document.getElementById("content-area");
+  const scrollTop = contentArea.scrollTop;
+  renderTabContent(tab);
+  contentArea.scrollTop = scrollTop;
+}
+
+document.getElementById("editor").addEventListener("input", (e) => {
+  const tab = getActiveTab();
+  if (!tab || !tab.editing) return;
+  tab.draft = e.target.value;
+  tab.dirty = tab.draft !== tab.content;
+  updateEditorStatus(tab);
+  clearTimeout(previewTimer);
+  previewTimer = setTimeout(() => updatePreview(tab), 200);
+});
+
+document.getElementById("btn-edit").addEventListener("click", toggleEditMode);
+document.getElementById("btn-render-settings").addEventListener("click", openRenderSettings);
+document.getElementById("btn-export").addEventListener("click", exportActiveTab);
+document.getElementById("render-raw-html").addEventListener("change", async (e) => {
+  renderProfile = { ...renderProfile, raw_html: e.target.checked };
+  await saveRenderProfile();
+});
+document.getElementById("render-settings-close").addEventListener("click", () => {
+  hideModal("render-settings-modal");
+});
+document.getElementById("watch-folder-mode").addEventListener("change", async (e) => {
+  if (!watchSettingsTab || !watchSettingsTab.watch) return;
+  try {
+    await invoke("set_folder_watch_mode", {
+      folder: watchSettingsTab.watch.folder,
+      mode: e.target.value || null,
+    });
+    await refreshWatchModes();
+  } catch (err) {
+    console.error("Failed to set watch mode:", err);
+  }
+});
+document.getElementById("watch-poll-interval").addEventListener("change", async (e) => {
+  const seconds = parseFloat(e.target.value);
+  if (!(seconds > 0)) return;
+  try {
+    const settings = await invoke("get_watch_settings");
+    settings.poll_interval_ms = Math.round(seconds * 1000);
+    await invoke("set_watch_settings", { settings });
+    await refreshWatchModes();
+  } catch (err) {
+    console.error("Failed to save watch settings:", err);
+  }
+});
+document.getElementById("watch-settings-close").addEventListener("click", () => {
+  hideModal("watch-settings-modal");
+});
+document.getElementById("btn-check-links").addEventListener("click", openLinkCheck);
+document.getElementById("link-check-document").addEventListener("click", () => {
+  const tab = getActiveTab();
+  if (tab) runLinkCheck(tab.path);
+});
+document.getElementById("link-check-folder").addEventListener("click", async () => {
+  const tab = getActiveTab();
+  const folder = await open({
+    directory: true,
+    defaultPath: tab ? tab.path.replace(/[\\/][^\\/]*$/, "") : undefined,
+  });
+  if (folder) runLinkCheck(folder);
+});
+document.getElementById("link-check-close").addEventListener("click", () => {
+  hideModal("link-check-modal");
+});
+document.getElementById("render-preset-select").addEventListener("change", async (e) => {
+  try {
+    renderProfile = await invoke("apply_render_preset", { preset: e.target.value });
+    renderProfileForm();
+    await rerenderAllTabs();
+  } catch (err) {
+    console.error("Failed to apply preset:", err);
+  }
+});
+
 // Comment management functions
 
 async function loadCommentsForFile(markdownPath) {
@@ -641,6 +1745,7 @@ function saveCommentsForFile() {
   const data = JSON.parse(JSON.stringify(commentsData));
   saveQueue = saveQueue
     .then(() => invoke("save_comments", { markdownPath: path, commentsData: data }))
+    .then(() => refreshOutline(path))
     .then(() => {
       const banner = document.getElementById("save-error-banner");
       if (banner) banner.style.display = "none";
@@ -652,6 +1757,14 @@ function saveCommentsForFile() {
     });
 }
 
+async function refreshOutline(path) {
+  const tab = tabs.find(t => t.path === path);
+  if (!tab || tab.content === null) return;
+  tab.outline = await invoke("extract_outline", { markdown: tab.content, markdownPath: path });
+  tab.headings = flattenOutline(tab.outline.sections);
+  if (tab.id === activeTabId) populateOutline(tab.outline);
+}
+
 function addComment(text) {
   if (selectedBlocks.length === 0) return;
 
@@ -663,6 +1776,10 @@ function addComment(text) {
     timestamp: Date.now(),
     resolved: false,
   };
+  const startLines = selectedBlocks
+    .map(block => Number(block.dataset.startLine))
+    .filter(line => line > 0);
+  if (startLines.length > 0) comment.line = Math.min(...startLines);
 
   commentsData.comments.push(comment);
   saveCommentsForFile();
@@ -870,11 +1987,12 @@ function updateBottomBar() {
       const chip = document.createElement("span");
       chip.className = "block-chip";
       const blockNum = blockId.match(/\d+$/)?.[0] || "?";
-      const typeMap = { heading: "H", para: "P", list: "Li", code: "C", quote: "Q" };
+      const typeMap = { heading: "H", para: "P", list: "Li", code: "C", quote: "Q", output: "Out" };
       const typeMatch = blockId.match(/^mkw-(\w+)-/);
       const prefix = typeMatch ? (typeMap[typeMatch[1]] || "B") : "B";
       chip.textContent = `${prefix}${blockNum}`;
-      chip.title = `${typeMatch ? typeMatch[1] : "block"} #${blockNum}`;
+      const lineRange = blockLineRange(blockId);
+      chip.title = `${typeMatch ? typeMatch[1] : "block"} #${blockNum}` + (lineRange ? ` (${lineRange})` : "");
       chip.onclick = () => {
         const block = document.getElementById(blockId);
         if (block) block.scrollIntoView({ behavior: "smooth", block: "center" });
@@ -942,6 +2060,14 @@ function generateReviewPrompt() {
       })
       .filter(content => content !== null);
 
+    const lineRanges = comment.block_ids
+      .map(blockLineRange)
+      .filter(range => range !== null);
+
+    if (lineRanges.length > 0) {
+      prompt += `Location: ${currentPath ? currentPath.split('/').pop() : "document"}, ${lineRanges.join(", ")}\n`;
+    }
+
     if (blockContents.length > 0) {
       prompt += `About the block(s):\n`;
       blockContents.forEach(content => {
@@ -960,8 +2086,16 @@ function generateReviewPrompt() {
   return prompt;
 }
 
+document.getElementById("content").addEventListener("click", (e) => {
+  const a = e.target.closest("a[href]");
+  if (!a) return;
+  e.preventDefault();
+  followLink(a).catch((err) => console.error("Failed to follow link:", err));
+});
+
 // Block selection event listener
 document.addEventListener("click", (e) => {
+  if (e.target.closest("#content a[href], #content .task-checkbox")) return;
   const block = e.target.closest(".commentable-block");
   if (block && !e.target.closest(".comment-badge")) {
     const multiSelect = e.metaKey || e.ctrlKey; // Cmd on Mac, Ctrl on Windows/Linux
@@ -975,6 +2109,8 @@ document.getElementById("btn-refresh").addEventListener("click", () => {
   if (tab) loadFileIntoTab(tab.id, tab.path);
 });
 document.getElementById("btn-open").addEventListener("click", openFileDialog);
+document.getElementById("btn-open-folder").addEventListener("click", openFolderDialog);
+document.getElementById("workspace-close").addEventListener("click", closeWorkspace);
 document.getElementById("history-button").addEventListener("click", (e) => {
   const dropdown = document.getElementById("history-dropdown");
   if (dropdown.style.display === "block") {
@@ -1018,6 +2154,9 @@ document.addEventListener("keydown", (e) => {
     const commentModal = document.getElementById("comment-modal");
     const reviewModal = document.getElementById("review-modal");
     const whisperModal = document.getElementById("whisper-settings-modal");
+    const renderModal = document.getElementById("render-settings-modal");
+    const linkCheckModal = document.getElementById("link-check-modal");
+    const watchModal = document.getElementById("watch-settings-modal");
 
     if (commentModal && commentModal.style.display === "flex") {
       e.preventDefault();
@@ -1036,6 +2175,35 @@ document.addEventListener("keydown", (e) => {
       hideModal("whisper-settings-modal");
       return;
     }
+
+    if (renderModal && renderModal.style.display === "flex") {
+      e.preventDefault();
+      hideModal("render-settings-modal");
+      return;
+    }
+
+    if (linkCheckModal && linkCheckModal.style.display === "flex") {
+      e.preventDefault();
+      hideModal("link-check-modal");
+      return;
+    }
+
+    if (watchModal && watchModal.style.display === "flex") {
+      e.preventDefault();
+      hideModal("watch-settings-modal");
+      return;
+    }
+  }
+
+  if ((e.metaKey || e.ctrlKey) && e.key === "e") {
+    e.preventDefault();
+    toggleEditMode();
+  }
+
+  if ((e.metaKey || e.ctrlKey) && e.key === "s") {
+    e.preventDefault();
+    const tab = getActiveTab();
+    if (tab && tab.editing) saveDraft(tab);
   }
 
   if ((e.metaKey || e.ctrlKey) && e.key === "w") {
@@ -1078,19 +2246,95 @@ sidebarHandle.addEventListener("mousedown", (e) => {
   document.addEventListener("mouseup", onUp);
 });
 
-listen("file-changed", async (event) => {
-  const changedPath = event.payload;
-
+async function reloadChangedFile(changedPath) {
   for (const tab of tabs.filter(t => t.path === changedPath)) {
+    // Our own saves come back as change events; the view already has them.
+    const hash = await invoke("hash_file", { path: tab.path }).catch(() => null);
+    if (hash && hash === tab.fileHash && !tab.hasError) continue;
+
+    if (tab.editing && tab.dirty) {
+      tab.diskChanged = true;
+      if (tab.id === activeTabId) updateEditorStatus(tab);
+      continue;
+    }
+
     if (tab.id === activeTabId) {
-      await loadFileIntoTab(tab.id, tab.path);
+      await loadFileIntoTab(tab.id, tab.path, true);
     } else {
+      if (tab.hasError) {
+        tab.hasError = false;
+        tab.displayName = tabDisplayName(tab.path);
+        updateTabBarUI();
+      }
       tab.content = null;
       tab.html = null;
+      tab.htmlBlocks = null;
+      tab.frontMatter = null;
+      tab.blocks = [];
+      tab.links = [];
       tab.headings = null;
+      tab.outline = null;
     }
   }
-});
+}
+
+listen("file-changed", (event) => reloadChangedFile(event.payload));
+
+// Deletions and renames are settled after a moment. inotify reports a
+// rename as a deletion first, and editors that save by moving the old file
+// aside and writing a new one look like a rename until the new file lands.
+const pendingMoves = new Map(); // old path -> timer
+
+function settleMoveLater(from, to) {
+  clearTimeout(pendingMoves.get(from));
+  pendingMoves.set(from, setTimeout(() => {
+    pendingMoves.delete(from);
+    settleMove(from, to);
+  }, 250));
+}
+
+async function settleMove(from, to) {
+  const moved = tabs.filter(t => t.path === from);
+  if (moved.length === 0) return;
+
+  const exists = await invoke("hash_file", { path: from }).then(() => true, () => false);
+  if (exists) {
+    await reloadChangedFile(from);
+    return;
+  }
+
+  if (to) {
+    for (const tab of moved) {
+      tab.path = to;
+      tab.displayName = tabDisplayName(to);
+    }
+    const watch = await invoke("watch_file", { path: to }).catch((e) => console.warn("Failed to watch file:", e));
+    for (const tab of moved) tab.watch = watch || null;
+    await invoke("unwatch_file", { path: from }).catch((e) => console.warn("Failed to unwatch file:", e));
+    const active = getActiveTab();
+    if (active && active.path === to) {
+      currentPath = to;
+      document.getElementById("toolbar-title").textContent = formatPath(to);
+      document.getElementById("toolbar-title").title = to;
+    }
+  } else {
+    for (const tab of moved) {
+      // Unsaved edits stay in the editor; saving reports the file missing.
+      if (tab.editing && tab.dirty) {
+        tab.diskChanged = true;
+        if (tab.id === activeTabId) updateEditorStatus(tab);
+        continue;
+      }
+      tab.hasError = true;
+      tab.displayName = `${tabDisplayName(tab.path)} (deleted)`;
+      if (tab.id === activeTabId) showErrorState(tab.id, tab.path);
+    }
+  }
+  updateTabBarUI();
+}
+
+listen("file-deleted", (event) => settleMoveLater(event.payload, null));
+listen("file-renamed", (event) => settleMoveLater(event.payload.from, event.payload.to));
 
 // Whisper file-watcher events — auto-refresh UI when models or settings
 // change on disk (e.g. external download, another instance, manual edit).
@@ -1421,7 +2665,7 @@ shortcutInput.addEventListener("keydown", async (e) => {
 
 listen("open-file", async (event) => {
   console.log("[DEBUG] open-file event received:", event.payload);
-  await openFileInNewTab(event.payload);
+  await openPath(event.payload);
   getCurrentWindow().show();
 });
 
@@ -1465,9 +2709,12 @@ document.getElementById("cli-result-ok").addEventListener("click", () => hideMod
 (async () => {
   await loadFileHistory();
 
+  const formats = await invoke("document_formats");
+  markdownExtensions = formats.find(f => f.name === "Markdown").extensions;
+
   const initialFile = await invoke("get_initial_file");
   if (initialFile) {
-    await openFileInNewTab(initialFile);
+    await openPath(initialFile);
   }
 
   const status = await invoke("check_cli_status");
@@ -1485,6 +2732,10 @@ listen("menu-open-file", () => {
   openFileDialog();
 });
 
+listen("menu-open-folder", () => {
+  openFolderDialog();
+});
+
 applyTheme(currentTheme);
 
 if (!currentPath) {